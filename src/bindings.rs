@@ -0,0 +1,324 @@
+//! Exposes the scene graph to javascript.
+//! The vertex and index data are returned as typed arrays viewing the memory of the module,
+//! so they can be uploaded to WebGL without copying. The views are only valid until the memory
+//! of the module grows, so they should be uploaded, or copied, right away.
+use std::rc::Rc;
+
+use js_sys::{Float32Array, Uint32Array};
+use wasm_bindgen::prelude::*;
+
+use crate::data::{Material, TextureMap};
+use crate::scene::{SceneGraph, SceneMesh, SceneNode};
+
+/// The loaded scene, shared by the nodes, meshes and materials taken from it.
+#[wasm_bindgen(js_name = SceneGraph)]
+pub struct JsSceneGraph {
+    scene: Rc<SceneGraph>,
+}
+
+/// Node of the scene.
+#[wasm_bindgen(js_name = SceneNode)]
+pub struct JsSceneNode {
+    scene: Rc<SceneGraph>,
+    index: usize,
+}
+
+/// Mesh of the scene, with its vertex and index data.
+#[wasm_bindgen(js_name = SceneMesh)]
+pub struct JsSceneMesh {
+    scene: Rc<SceneGraph>,
+    index: usize,
+}
+
+/// Material of the scene.
+#[wasm_bindgen(js_name = Material)]
+pub struct JsMaterial {
+    scene: Rc<SceneGraph>,
+    index: usize,
+}
+
+impl JsSceneGraph {
+    /// Share the scene with javascript.
+    pub fn new(scene: SceneGraph) -> Self {
+        JsSceneGraph { scene: Rc::new(scene) }
+    }
+}
+
+#[wasm_bindgen(js_class = SceneGraph)]
+impl JsSceneGraph {
+    #[wasm_bindgen(getter)]
+    pub fn node_count(&self) -> usize {
+        self.scene.nodes.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn mesh_count(&self) -> usize {
+        self.scene.meshes.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn material_count(&self) -> usize {
+        self.scene.materials.len()
+    }
+
+    pub fn node(&self, index: usize) -> Option<JsSceneNode> {
+        self.scene.nodes.get(index)
+            .map(|_| JsSceneNode { scene: Rc::clone(&self.scene), index })
+    }
+
+    pub fn mesh(&self, index: usize) -> Option<JsSceneMesh> {
+        self.scene.meshes.get(index)
+            .map(|_| JsSceneMesh { scene: Rc::clone(&self.scene), index })
+    }
+
+    pub fn material(&self, index: usize) -> Option<JsMaterial> {
+        self.scene.materials.get(index)
+            .map(|_| JsMaterial { scene: Rc::clone(&self.scene), index })
+    }
+
+    /// Indices of the nodes without a parent.
+    pub fn roots(&self) -> Uint32Array {
+        copied_indices(&self.scene.roots())
+    }
+
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.scene.find_node(name)
+    }
+
+    pub fn find_mesh(&self, name: &str) -> Option<usize> {
+        self.scene.find_mesh(name)
+    }
+
+    pub fn find_material(&self, name: &str) -> Option<usize> {
+        self.scene.find_material(name)
+    }
+}
+
+impl JsSceneNode {
+    fn node(&self) -> &SceneNode {
+        &self.scene.nodes[self.index]
+    }
+}
+
+#[wasm_bindgen(js_class = SceneNode)]
+impl JsSceneNode {
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.node().name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn group(&self) -> Option<String> {
+        self.node().group.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn parent(&self) -> Option<usize> {
+        self.node().parent.as_ref().map(|x| x.index)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn children(&self) -> Uint32Array {
+        copied_indices(&self.node().children)
+    }
+
+    /// Indices of the meshes of the node.
+    #[wasm_bindgen(getter)]
+    pub fn meshes(&self) -> Uint32Array {
+        copied_indices(&self.node().meshes)
+    }
+
+    /// Transformation relative to the parent, as 16 floats in column-major order.
+    #[wasm_bindgen(getter)]
+    pub fn transformation(&self) -> Vec<f32> {
+        let matrix: &[f32; 16] = self.node().transformation.as_ref();
+        matrix.to_vec()
+    }
+
+    /// Transformation within the world, as 16 floats in column-major order.
+    pub fn world_transform(&self) -> Vec<f32> {
+        let matrix = self.scene.world_transform(self.index);
+        let matrix: &[f32; 16] = matrix.as_ref();
+        matrix.to_vec()
+    }
+}
+
+impl JsSceneMesh {
+    fn mesh(&self) -> &SceneMesh {
+        &self.scene.meshes[self.index]
+    }
+}
+
+#[wasm_bindgen(js_class = SceneMesh)]
+impl JsSceneMesh {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.mesh().name.clone()
+    }
+
+    /// Index of the material in the scene.
+    #[wasm_bindgen(getter)]
+    pub fn material(&self) -> Option<usize> {
+        self.mesh().material.as_ref().map(|x| x.index)
+    }
+
+    /// Positions, as xyz for each vertex.
+    pub fn positions(&self) -> Float32Array {
+        float_view(&self.mesh().gv)
+    }
+
+    /// Normals, as xyz for each vertex, empty when the mesh has none.
+    pub fn normals(&self) -> Float32Array {
+        float_view(&self.mesh().vn)
+    }
+
+    /// Texture coordinates, as uv for each vertex, empty when the mesh has none.
+    pub fn uvs(&self) -> Float32Array {
+        float_view(&self.mesh().vt)
+    }
+
+    /// Colors, as RGBA for each vertex, empty when the mesh has none.
+    pub fn colors(&self) -> Float32Array {
+        float_view(&self.mesh().colors)
+    }
+
+    /// Tangents, as xyz and handedness for each vertex, empty unless they were generated.
+    pub fn tangents(&self) -> Float32Array {
+        float_view(&self.mesh().tangents)
+    }
+
+    /// Every index, ordered as triangles, line strips and then points.
+    pub fn indices(&self) -> Uint32Array {
+        index_view(&self.mesh().face_indices)
+    }
+
+    /// Indices to draw as triangles.
+    pub fn triangles(&self) -> Uint32Array {
+        let mesh = self.mesh();
+        index_view(&mesh.face_indices[mesh.triangles.clone()])
+    }
+
+    /// Indices to draw as points.
+    pub fn points(&self) -> Uint32Array {
+        let mesh = self.mesh();
+        index_view(&mesh.face_indices[mesh.points.clone()])
+    }
+
+    /// Start and end of each line strip within `indices`, one pair after the other.
+    pub fn line_strips(&self) -> Uint32Array {
+        let ranges: Vec<usize> = self.mesh().line_strips.iter()
+            .flat_map(|x| vec![x.start, x.end])
+            .collect();
+        copied_indices(&ranges)
+    }
+
+    /// Indices of the triangles of the group, to draw it on its own.
+    pub fn group_triangles(&self, name: &str) -> Uint32Array {
+        copied_indices(&self.mesh().group_triangles(name))
+    }
+}
+
+impl JsMaterial {
+    fn material(&self) -> &Material {
+        &self.scene.materials[self.index]
+    }
+}
+
+#[wasm_bindgen(js_class = Material)]
+impl JsMaterial {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.material().name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ambient(&self) -> Vec<f32> {
+        self.material().ambient.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn diffuse(&self) -> Vec<f32> {
+        self.material().diffuse.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn specular(&self) -> Vec<f32> {
+        self.material().specular.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn emissive(&self) -> Vec<f32> {
+        self.material().emissive.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn shininess(&self) -> f32 {
+        self.material().shininess
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dissolve(&self) -> f32 {
+        self.material().dissolve
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn diffuse_texture(&self) -> Option<String> {
+        texture_path(&self.material().texture_diffuse)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn normal_texture(&self) -> Option<String> {
+        texture_path(&self.material().texture_normal)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn bump_texture(&self) -> Option<String> {
+        texture_path(&self.material().texture_bump)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn specular_texture(&self) -> Option<String> {
+        texture_path(&self.material().texture_specular)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn emissive_texture(&self) -> Option<String> {
+        texture_path(&self.material().texture_emissive)
+    }
+}
+
+/// Path of the texture, resolved relative to the .mtl file to fetch it.
+fn texture_path(texture: &Option<TextureMap>) -> Option<String> {
+    texture.as_ref().map(|x| x.resolved.clone())
+}
+
+/// View of the floats within the memory of the module.
+fn float_view(values: &[f32]) -> Float32Array {
+    // The scene isn't changed while it is shared, so the data stays in place
+    // until the memory grows.
+    unsafe { Float32Array::view(values) }
+}
+
+/// View of the indices within the memory of the module, where they are 32 bits wide.
+#[cfg(target_pointer_width = "32")]
+fn index_view(indices: &[usize]) -> Uint32Array {
+    let indices = unsafe { std::slice::from_raw_parts(indices.as_ptr() as *const u32, indices.len()) };
+    unsafe { Uint32Array::view(indices) }
+}
+
+/// Copy of the indices, as they are wider than 32 bits outside of wasm32.
+#[cfg(not(target_pointer_width = "32"))]
+fn index_view(indices: &[usize]) -> Uint32Array {
+    copied_indices(indices)
+}
+
+/// Copy of the indices into a new array.
+fn copied_indices(indices: &[usize]) -> Uint32Array {
+    let indices: Vec<u32> = indices.iter().map(|x| *x as u32).collect();
+    Uint32Array::from(&indices[..])
+}
@@ -0,0 +1,289 @@
+//! Bounding volumes of the meshes, nodes and scenes, to frame and cull them.
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+/// Box aligned with the axes, empty until a point is added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// Smallest coordinates of the points.
+    pub min: Vector3<f32>,
+    /// Largest coordinates of the points.
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    /// Create an empty box.
+    pub fn new() -> Self {
+        Aabb {
+            min: Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    /// Smallest box holding the points.
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        let mut aabb = Aabb::new();
+        for point in points {
+            aabb.add_point(*point);
+        }
+        aabb
+    }
+
+    /// True, if no point has been added.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x
+    }
+
+    /// Grow the box to hold the point.
+    pub fn add_point(&mut self, point: Vector3<f32>) {
+        let (min, max) = (self.min, self.max);
+        self.min = Vector3::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+        self.max = Vector3::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+    }
+
+    /// Grow the box to hold the other one.
+    pub fn merge(&mut self, other: &Aabb) {
+        if !other.is_empty() {
+            self.add_point(other.min);
+            self.add_point(other.max);
+        }
+    }
+
+    /// Point in the middle of the box.
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Length of the box along each axis.
+    pub fn size(&self) -> Vector3<f32> {
+        if self.is_empty() {
+            Vector3::new(0.0, 0.0, 0.0)
+        } else {
+            self.max - self.min
+        }
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vector3::new(a.x, a.y, a.z), Vector3::new(b.x, a.y, a.z),
+            Vector3::new(a.x, b.y, a.z), Vector3::new(b.x, b.y, a.z),
+            Vector3::new(a.x, a.y, b.z), Vector3::new(b.x, a.y, b.z),
+            Vector3::new(a.x, b.y, b.z), Vector3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Box aligned with the axes holding this box once transformed.
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        let corners: Vec<Vector3<f32>> = self.corners().iter()
+            .map(|x| transform_point(transform, *x))
+            .collect();
+        Aabb::from_points(&corners)
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::new()
+    }
+}
+
+/// Sphere holding all the points, with a negative radius while empty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Create an empty sphere.
+    pub fn new() -> Self {
+        BoundingSphere {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            radius: -1.0,
+        }
+    }
+
+    /// Sphere around the middle of the points, reaching the farthest of them.
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        if points.is_empty() {
+            return BoundingSphere::new();
+        }
+        let center = Aabb::from_points(points).center();
+        let radius = points.iter()
+            .map(|x| (x - center).magnitude())
+            .fold(0.0, f32::max);
+        BoundingSphere { center, radius }
+    }
+
+    /// True, if the sphere holds no point.
+    pub fn is_empty(&self) -> bool {
+        self.radius < 0.0
+    }
+}
+
+impl Default for BoundingSphere {
+    fn default() -> Self {
+        BoundingSphere::new()
+    }
+}
+
+/// Box along the principal axes of the points, usually tighter than the one along the axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrientedBox {
+    /// Point in the middle of the box.
+    pub center: Vector3<f32>,
+    /// Unit directions of the edges of the box.
+    pub axes: [Vector3<f32>; 3],
+    /// Half of the length of the box along each of the axes.
+    pub half_extents: Vector3<f32>,
+}
+
+impl OrientedBox {
+    /// Create an empty box along the axes of the world.
+    pub fn new() -> Self {
+        OrientedBox {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            axes: [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()],
+            half_extents: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Box along the eigenvectors of the covariance of the points.
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        if points.is_empty() {
+            return OrientedBox::new();
+        }
+        let count = points.len() as f32;
+        let mean = points.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, x| sum + x) / count;
+        let mut covariance = [[0.0f32; 3]; 3];
+        for point in points {
+            let d = point - mean;
+            let d = [d.x, d.y, d.z];
+            for (i, row) in covariance.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value += d[i] * d[j] / count;
+                }
+            }
+        }
+        let axes = eigenvectors(covariance);
+        let mut min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = -min;
+        for point in points {
+            for i in 0..3 {
+                let distance = point.dot(axes[i]);
+                min[i] = min[i].min(distance);
+                max[i] = max[i].max(distance);
+            }
+        }
+        let middle = (min + max) / 2.0;
+        OrientedBox {
+            center: axes[0] * middle.x + axes[1] * middle.y + axes[2] * middle.z,
+            axes,
+            half_extents: (max - min) / 2.0,
+        }
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        let [u, v, w] = self.axes;
+        let e = self.half_extents;
+        let mut corners = [self.center; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            *corner += u * e.x * sign(1) + v * e.y * sign(2) + w * e.z * sign(4);
+        }
+        corners
+    }
+}
+
+impl Default for OrientedBox {
+    fn default() -> Self {
+        OrientedBox::new()
+    }
+}
+
+/// Every bounding volume of the same points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+    pub oriented: OrientedBox,
+}
+
+impl Bounds {
+    /// Create empty bounds.
+    pub fn new() -> Self {
+        Bounds {
+            aabb: Aabb::new(),
+            sphere: BoundingSphere::new(),
+            oriented: OrientedBox::new(),
+        }
+    }
+
+    /// Bounding volumes of the points.
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        Bounds {
+            aabb: Aabb::from_points(points),
+            sphere: BoundingSphere::from_points(points),
+            oriented: OrientedBox::from_points(points),
+        }
+    }
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds::new()
+    }
+}
+
+/// Apply the transformation to the point.
+pub fn transform_point(transform: &Matrix4<f32>, point: Vector3<f32>) -> Vector3<f32> {
+    let p = transform * Vector4::new(point.x, point.y, point.z, 1.0);
+    if p.w != 0.0 && p.w != 1.0 {
+        p.truncate() / p.w
+    } else {
+        p.truncate()
+    }
+}
+
+/// Unit eigenvectors of the symmetric matrix, by Jacobi rotations.
+/// They form a right-handed basis, sorted by decreasing eigenvalue.
+fn eigenvectors(mut a: [[f32; 3]; 3]) -> [Vector3<f32>; 3] {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..32 {
+        let (p, q) = [(0, 1), (0, 2), (1, 2)].iter()
+            .cloned()
+            .fold((0, 1), |best, (i, j)| {
+                if a[i][j].abs() > a[best.0][best.1].abs() { (i, j) } else { best }
+            });
+        if a[p][q].abs() < 1e-12 {
+            break;
+        }
+        let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let c = 1.0 / (t * t + 1.0).sqrt();
+        let s = t * c;
+        for row in a.iter_mut() {
+            let (ap, aq) = (row[p], row[q]);
+            row[p] = c * ap - s * aq;
+            row[q] = s * ap + c * aq;
+        }
+        let (row_p, row_q) = (a[p], a[q]);
+        a[p] = [0, 1, 2].map(|k| c * row_p[k] - s * row_q[k]);
+        a[q] = [0, 1, 2].map(|k| s * row_p[k] + c * row_q[k]);
+        for row in v.iter_mut() {
+            let (vp, vq) = (row[p], row[q]);
+            row[p] = c * vp - s * vq;
+            row[q] = s * vp + c * vq;
+        }
+    }
+    let mut order = [0, 1, 2];
+    order.sort_by(|x, y| a[*y][*y].partial_cmp(&a[*x][*x]).unwrap_or(std::cmp::Ordering::Equal));
+    let column = |i: usize| Vector3::new(v[0][i], v[1][i], v[2][i]).normalize();
+    let (u, w) = (column(order[0]), column(order[1]));
+    [u, w, u.cross(w)]
+}
@@ -1,11 +1,14 @@
 /// Contains data structures that holds the parsed information from the file.
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Deref;
 use cgmath::{Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+use crate::error::ObjError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FaceType {
     Point,
     Line,
@@ -15,29 +18,118 @@ pub enum FaceType {
 
 type IndexArray = Vec<usize>;
 
+/// Active material along with the library of materials.
+pub type MaterialLibrary = (Rc<RefCell<Material>>, HashMap<String, Rc<RefCell<Material>>>);
+
+/// Indices of the active groups along with the names of every group.
+pub type GroupLibrary = (Vec<usize>, Vec<String>);
+
+/// Channel of the texture used for scalar and bump textures.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextureChannel {
+    Red,
+    Green,
+    Blue,
+    Matte,
+    Luminance,
+    Depth,
+}
+
+/// Holds the texture file along with the options written before its name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextureMap {
+    /// name of the texture file, as written in the .mtl file
+    pub path: String,
+    /// name of the texture file, resolved relative to the .mtl file
+    pub resolved: String,
+    /// offset of the texture origin, `-o`
+    pub offset: [f32; 3],
+    /// scale of the texture, `-s`
+    pub scale: [f32; 3],
+    /// turbulence of the texture, `-t`
+    pub turbulence: [f32; 3],
+    /// clamp the texture coordinates to 0..1, `-clamp`
+    pub clamp: bool,
+    /// multiplier for the bump texture, `-bm`
+    pub bump_multiplier: f32,
+    /// horizontal blending, `-blendu`
+    pub blend_u: bool,
+    /// vertical blending, `-blendv`
+    pub blend_v: bool,
+    /// base value added to the texture, `-mm`
+    pub base: f32,
+    /// gain applied to the texture, `-mm`
+    pub gain: f32,
+    /// channel used for scalar and bump textures, `-imfchan`
+    pub channel: Option<TextureChannel>,
+    /// resolution of the texture, `-texres`
+    pub resolution: Option<u32>,
+    /// sharpness boost of the mip-maps, `-boost`
+    pub boost: Option<f32>,
+    /// color correction, `-cc`
+    pub color_correction: bool,
+}
+
+/// Holds the physically based extension of the material.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PbrMaterial {
+    /// roughness, `Pr`
+    pub roughness: f32,
+    /// metallic, `Pm`
+    pub metallic: f32,
+    /// sheen, `Ps`
+    pub sheen: f32,
+    /// clearcoat thickness, `Pc`
+    pub clearcoat_thickness: f32,
+    /// clearcoat roughness, `Pcr`
+    pub clearcoat_roughness: f32,
+    /// anisotropy, `aniso`
+    pub anisotropy: f32,
+    /// rotation of the anisotropy, `anisor`
+    pub anisotropy_rotation: f32,
+    /// the roughness texture
+    pub texture_roughness: Option<TextureMap>,
+    /// the metallic texture
+    pub texture_metallic: Option<TextureMap>,
+    /// the sheen texture
+    pub texture_sheen: Option<TextureMap>,
+    /// the combined roughness, metallic and ambient occlusion texture
+    pub texture_rma: Option<TextureMap>,
+}
+
 /// Holds different information about the materials present within the scene.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Material {
     /// material name
     pub name: String,
-    /// name of the texture
-    pub texture: String,
-    /// name of the ambient texture
-    pub texture_ambient: String,
-    /// name of the diffuse texture
-    pub texture_diffuse: String,
-    /// name of the emissive texture
-    pub texture_emissive: String,
-    /// name of the normal texture
-    pub texture_normal: String,
-    /// name of the specular texture
-    pub texture_specular: String,
+    /// the ambient texture
+    pub texture_ambient: Option<TextureMap>,
+    /// the diffuse texture
+    pub texture_diffuse: Option<TextureMap>,
+    /// the emissive texture
+    pub texture_emissive: Option<TextureMap>,
+    /// the normal texture
+    pub texture_normal: Option<TextureMap>,
+    /// the specular texture
+    pub texture_specular: Option<TextureMap>,
+    /// the specular exponent texture
+    pub texture_shininess: Option<TextureMap>,
+    /// the dissolve texture
+    pub texture_dissolve: Option<TextureMap>,
+    /// the bump texture
+    pub texture_bump: Option<TextureMap>,
+    /// the displacement texture
+    pub texture_displacement: Option<TextureMap>,
+    /// the decal texture
+    pub texture_decal: Option<TextureMap>,
     /// ambient color
     pub ambient: [f32; 3],
     /// diffuse color
     pub diffuse: [f32; 3],
-    /// emissive color
+    /// transmission filter color
     pub transmission: [f32; 3],
+    /// emissive color
+    pub emissive: [f32; 3],
     /// specular color
     pub specular: [f32; 3],
     /// shininess factor:
@@ -46,28 +138,35 @@ pub struct Material {
     pub illumination: Option<u8>,
     /// refractive index
     pub ri: f32,
+    /// dissolve factor, 1.0 is fully opaque
+    pub dissolve: f32,
+    /// dissolve depends on the surface orientation, `d -halo`
+    pub dissolve_halo: bool,
+    /// physically based properties, if the material has any
+    pub pbr: Option<PbrMaterial>,
 
 }
 
 /// Holds the information about the mesh.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Mesh {
     /// The name for the mesh
     pub name: String,
     /// Array with all stored faces
     pub faces: Vec<Face>,
     /// Assigned material
+    #[serde(with = "crate::serialize::material_name")]
     pub material: Rc<RefCell<Material>>,
     /// Number of stored indices.
     pub num_indices: u32,
     /// True, if normals are stored.
-    has_normals: bool,
+    pub has_normals: bool,
     /// True, if vertex colors are stored.
-    has_vertex_colors: bool,
+    pub has_vertex_colors: bool,
 }
 
 /// Holds the structure for face
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Face {
     /// FaceType
     pub face_type: FaceType,
@@ -78,26 +177,33 @@ pub struct Face {
     /// Texture coordinates indices
     pub textures: IndexArray,
     /// Pointer to assigned material
+    #[serde(with = "crate::serialize::material_name")]
     pub material: Rc<RefCell<Material>>,
+    /// Smoothing group of the face, 0 if the face isn't smoothed
+    pub smoothing_group: u32,
+    /// Indices of the groups the face belongs to, in the group library
+    pub groups: Vec<usize>,
+    /// Merging group of the face, 0 if the face isn't merged
+    pub merging_group: u32,
 }
 
 /// Data structure to hold the objects present withing the mesh file.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Object {
     /// Name of the object
     pub name: String,
     /// Transformations
+    #[serde(with = "crate::serialize::matrix")]
     pub transform: Matrix4<f32>,
-    /// Points to sub objects within this object.
-    pub sub_objects: Vec<usize>,
     /// Assigned meshes
     pub meshes: Vec<usize>,
 }
 
-/// Data Structure to hold model
-#[derive(Clone, Debug)]
+/// Data Structure to hold model.
+/// The materials are serialized once in the library, and referred to by name from the meshes and faces.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Model {
-    // TODO: Implement group later.
     /// Model name
     pub model_name: String,
     /// List ob assigned objects
@@ -105,23 +211,33 @@ pub struct Model {
     /// Pointer to current object
     pub cur_obj: usize,
     /// Pointer to current material
-    pub matlib: (Rc<RefCell<Material>>, HashMap<String, Rc<RefCell<Material>>>),
-    /// Pointer to default material
-    pub default_material: Option<Material>,
-    /// map of group library (active group name, a dictionary of list of faces.)
-    pub grplib: (String, HashMap<String, Vec<usize>>),
+    #[serde(with = "crate::serialize::material_library")]
+    pub matlib: MaterialLibrary,
+    /// Current smoothing group
+    pub cur_smoothing_group: u32,
+    /// Pointer to default material, used by the faces without a known material
+    #[serde(with = "crate::serialize::material")]
+    pub default_material: Rc<RefCell<Material>>,
+    /// Group library, with the groups of the next faces and the names of every group
+    pub grplib: GroupLibrary,
+    /// Current merging group
+    pub cur_merging_group: u32,
+    /// Resolution of each merging group
+    pub merging_resolutions: BTreeMap<u32, f32>,
     /// Vector with all generated vertices
     pub gv: Vec<f32>,
     /// vector with all generated normals
     pub vn: Vec<f32>,
     /// vector with all textures
     pub vt: Vec<f32>,
-    /// vector with all vertex colors
+    /// vector with all vertex colors, as RGBA for each vertex
     pub colors: Vec<f32>,
     /// Current mesh instance
     pub cur_mesh: usize,
     /// Vector with stored meshes
     pub meshes: Vec<Mesh>,
+    /// Problems that didn't stop the parsing, such as unknown materials
+    pub warnings: Vec<ObjError>,
 }
 
 impl Material {
@@ -129,23 +245,100 @@ impl Material {
     pub fn new() -> Self {
         Material {
             name: String::new(),
-            texture: String::new(),
-            texture_ambient: String::new(),
-            texture_diffuse: String::new(),
-            texture_emissive: String::new(),
-            texture_normal: String::new(),
-            texture_specular: String::new(),
+            texture_ambient: None,
+            texture_diffuse: None,
+            texture_emissive: None,
+            texture_normal: None,
+            texture_specular: None,
+            texture_shininess: None,
+            texture_dissolve: None,
+            texture_bump: None,
+            texture_displacement: None,
+            texture_decal: None,
             ambient: [0.0; 3],
             diffuse: [0.0; 3],
             transmission: [0.0; 3],
+            emissive: [0.0; 3],
             specular: [0.0; 3],
             shininess: 0.0,
             illumination: None,
             ri: 1.0,
+            dissolve: 1.0,
+            dissolve_halo: false,
+            pbr: None,
+        }
+    }
+
+    /// Create the light grey material used when the faces have no known material.
+    pub fn default_material() -> Self {
+        let mut material = Material::new();
+        material.name = String::from("default");
+        material.ambient = [0.2; 3];
+        material.diffuse = [0.8; 3];
+        material.illumination = Some(1);
+        material
+    }
+
+    /// Transparency of the material, the inverse of dissolve.
+    pub fn transparency(&self) -> f32 {
+        1.0 - self.dissolve
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::new()
+    }
+}
+
+impl TextureMap {
+    /// Create a texture map with default options for the given file.
+    pub fn new(path: &str) -> Self {
+        TextureMap {
+            path: path.to_string(),
+            resolved: path.to_string(),
+            offset: [0.0; 3],
+            scale: [1.0; 3],
+            turbulence: [0.0; 3],
+            clamp: false,
+            bump_multiplier: 1.0,
+            blend_u: true,
+            blend_v: true,
+            base: 0.0,
+            gain: 1.0,
+            channel: None,
+            resolution: None,
+            boost: None,
+            color_correction: false,
+        }
+    }
+}
+
+impl PbrMaterial {
+    /// Create physically based properties of a rough dielectric material.
+    pub fn new() -> Self {
+        PbrMaterial {
+            roughness: 1.0,
+            metallic: 0.0,
+            sheen: 0.0,
+            clearcoat_thickness: 0.0,
+            clearcoat_roughness: 0.0,
+            anisotropy: 0.0,
+            anisotropy_rotation: 0.0,
+            texture_roughness: None,
+            texture_metallic: None,
+            texture_sheen: None,
+            texture_rma: None,
         }
     }
 }
 
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial::new()
+    }
+}
+
 impl Mesh {
     /// Create a new mesh for parsing.
     pub fn new() -> Self {
@@ -160,6 +353,12 @@ impl Mesh {
     }
 }
 
+impl Default for Mesh {
+    fn default() -> Self {
+        Mesh::new()
+    }
+}
+
 impl Face {
     /// Create a new face for parsing.
     pub fn new() -> Self
@@ -170,38 +369,63 @@ impl Face {
             normals: IndexArray::new(),
             textures: IndexArray::new(),
             material: Rc::new(RefCell::new(Material::new())),
+            smoothing_group: 0,
+            groups: Vec::new(),
+            merging_group: 0,
         }
     }
 }
 
+impl Default for Face {
+    fn default() -> Self {
+        Face::new()
+    }
+}
+
 impl Object {
     /// Create a object for parsing.
     pub fn new() -> Self {
         Object {
             name: String::new(),
             transform: Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)),
-            sub_objects: Vec::new(),
             meshes: Vec::new(),
         }
     }
 }
 
+impl Default for Object {
+    fn default() -> Self {
+        Object::new()
+    }
+}
+
 impl Model {
     /// Create a model for parsing.
     pub fn new() -> Self {
+        let default_material = Rc::new(RefCell::new(Material::default_material()));
         Model {
             model_name: String::new(),
             objects: Vec::new(),
             cur_obj: 0,
-            matlib: (Rc::new(RefCell::new(Material::new())), HashMap::new()),
-            default_material: None,
-            grplib: (String::new(), HashMap::new()),
+            matlib: (Rc::clone(&default_material), HashMap::new()),
+            cur_smoothing_group: 0,
+            default_material,
+            grplib: (Vec::new(), Vec::new()),
+            cur_merging_group: 0,
+            merging_resolutions: BTreeMap::new(),
             gv: Vec::new(),
             vn: Vec::new(),
             vt: Vec::new(),
             colors: Vec::new(),
             cur_mesh: 0,
             meshes: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 }
+
+impl Default for Model {
+    fn default() -> Self {
+        Model::new()
+    }
+}
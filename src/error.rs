@@ -0,0 +1,135 @@
+//! Errors reported while parsing the .obj and .mtl files.
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "web_sys")]
+use wasm_bindgen::prelude::JsValue;
+
+/// Position of the offending token within the parsed file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    /// Name of the file being parsed.
+    pub file: String,
+    /// Line number, starting from 1, or 0 when the error concerns the whole file.
+    pub line: usize,
+    /// Column of the token within the line, starting from 1, or 0 for the whole file.
+    pub column: usize,
+}
+
+impl Location {
+    /// Locate the `token` within the `source` line it was split from.
+    pub fn new(file: &str, line: usize, source: &str, token: &str) -> Self {
+        let start = source.as_ptr() as usize;
+        let offset = (token.as_ptr() as usize).wrapping_sub(start);
+        let column = source
+            .get(..offset)
+            .map_or(1, |prefix| prefix.chars().count() + 1);
+        Location {
+            file: file.to_string(),
+            line,
+            column,
+        }
+    }
+
+    /// Locate the whole file, for the errors that don't come from one of its lines.
+    pub fn whole_file(file: &str) -> Self {
+        Location {
+            file: file.to_string(),
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.file)
+        } else if self.file.is_empty() {
+            write!(f, "line {}, column {}", self.line, self.column)
+        } else {
+            write!(f, "{}:{}:{}", self.file, self.line, self.column)
+        }
+    }
+}
+
+/// Describes why a statement in the .obj or .mtl file couldn't be read.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ObjError {
+    /// The token should have been a floating point number.
+    BadFloat { location: Location, token: String },
+    /// The token should have been a vertex, texture or normal index.
+    BadIndex { location: Location, token: String },
+    /// `usemtl` refers to a material that isn't in the library.
+    UnknownMaterial { location: Location, name: String },
+    /// The index refers past the data that has been read so far.
+    OutOfRange { location: Location, token: String, count: usize },
+    /// The statement is missing components or has too many of them.
+    MalformedStatement { location: Location, token: String },
+    /// The referenced file couldn't be read.
+    MissingResource { location: Location, path: String, reason: String },
+}
+
+impl ObjError {
+    /// Location of the offending token.
+    pub fn location(&self) -> &Location {
+        match self {
+            ObjError::BadFloat { location, .. }
+            | ObjError::BadIndex { location, .. }
+            | ObjError::UnknownMaterial { location, .. }
+            | ObjError::OutOfRange { location, .. }
+            | ObjError::MalformedStatement { location, .. }
+            | ObjError::MissingResource { location, .. } => location,
+        }
+    }
+
+    /// The offending token as it was written in the file.
+    pub fn token(&self) -> &str {
+        match self {
+            ObjError::BadFloat { token, .. }
+            | ObjError::BadIndex { token, .. }
+            | ObjError::OutOfRange { token, .. }
+            | ObjError::MalformedStatement { token, .. } => token,
+            ObjError::UnknownMaterial { name, .. } => name,
+            ObjError::MissingResource { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::BadFloat { location, token } => {
+                write!(f, "{}: expected a number, found `{}`", location, token)
+            }
+            ObjError::BadIndex { location, token } => {
+                write!(f, "{}: expected an index, found `{}`", location, token)
+            }
+            ObjError::UnknownMaterial { location, name } => {
+                write!(f, "{}: unknown material `{}`", location, name)
+            }
+            ObjError::OutOfRange { location, token, count } => write!(
+                f,
+                "{}: index `{}` is out of range, only {} defined",
+                location, token, count
+            ),
+            ObjError::MalformedStatement { location, token } => {
+                write!(f, "{}: malformed statement near `{}`", location, token)
+            }
+            ObjError::MissingResource { location, path, reason } => {
+                write!(f, "{}: can't read `{}`: {}", location, path, reason)
+            }
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+#[cfg(feature = "web_sys")]
+impl From<ObjError> for JsValue {
+    fn from(error: ObjError) -> Self {
+        js_sys::Error::new(&error.to_string()).into()
+    }
+}
@@ -0,0 +1,312 @@
+//! Exports the scene graph as glTF 2.0, either as JSON with a separate binary buffer or as GLB.
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use serde_json::{json, Map, Value};
+
+use crate::data::{Material, TextureMap};
+use crate::scene::{SceneGraph, SceneMesh};
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const POINTS: u32 = 0;
+const LINES: u32 = 1;
+const TRIANGLES: u32 = 4;
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+/// Write the scene as the glTF JSON, referring to the binary buffer at `bin_uri`.
+/// Returns the JSON and the content of the binary buffer.
+pub fn to_gltf(scene: &SceneGraph, bin_uri: &str) -> (String, Vec<u8>) {
+    let (mut document, bin) = document(scene);
+    if let Some(buffer) = document["buffers"].get_mut(0) {
+        buffer["uri"] = json!(encode_uri(bin_uri));
+    }
+    (document.to_string(), bin)
+}
+
+/// Write the scene as a single GLB buffer, holding the JSON and the binary data.
+pub fn to_glb(scene: &SceneGraph) -> Vec<u8> {
+    let (document, bin) = document(scene);
+    let mut json = document.to_string().into_bytes();
+    pad(&mut json, b' ');
+    let mut glb = Vec::with_capacity(28 + json.len() + bin.len());
+    let length = 12 + 8 + json.len() + if bin.is_empty() { 0 } else { 8 + bin.len() };
+    for value in &[GLB_MAGIC, 2, length as u32, json.len() as u32, GLB_JSON] {
+        glb.extend_from_slice(&value.to_le_bytes());
+    }
+    glb.extend_from_slice(&json);
+    if !bin.is_empty() {
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_BIN.to_le_bytes());
+        glb.extend_from_slice(&bin);
+    }
+    glb
+}
+
+/// Binary buffer along with the views and accessors of its data.
+struct Buffers {
+    bin: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Buffers {
+    /// Add the floats, `size` for each element, as an accessor of the given type.
+    fn push_floats(&mut self, values: &[f32], size: usize, kind: &str, bounds: bool) -> usize {
+        let offset = self.bin.len();
+        for value in values {
+            self.bin.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut accessor = json!({
+            "bufferView": self.push_view(offset, ARRAY_BUFFER),
+            "componentType": FLOAT,
+            "count": values.len() / size,
+            "type": kind,
+        });
+        if bounds {
+            let mut min = vec![f32::INFINITY; size];
+            let mut max = vec![f32::NEG_INFINITY; size];
+            for element in values.chunks(size) {
+                for (i, value) in element.iter().enumerate() {
+                    min[i] = min[i].min(*value);
+                    max[i] = max[i].max(*value);
+                }
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Add the indices as an accessor of unsigned integers.
+    fn push_indices(&mut self, indices: &[usize]) -> usize {
+        let offset = self.bin.len();
+        for index in indices {
+            self.bin.extend_from_slice(&(*index as u32).to_le_bytes());
+        }
+        let view = self.push_view(offset, ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// Add the view of the data from `offset` to the end of the buffer, which is then aligned.
+    fn push_view(&mut self, offset: usize, target: u32) -> usize {
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.bin.len() - offset,
+            "target": target,
+        }));
+        pad(&mut self.bin, 0);
+        self.views.len() - 1
+    }
+}
+
+/// Build the glTF document and its binary buffer.
+fn document(scene: &SceneGraph) -> (Value, Vec<u8>) {
+    let mut buffers = Buffers { bin: Vec::new(), views: Vec::new(), accessors: Vec::new() };
+    let primitives: Vec<Vec<Value>> = scene.meshes.iter()
+        .map(|mesh| mesh_primitives(mesh, &mut buffers))
+        .collect();
+
+    let mut meshes = Vec::new();
+    let mut nodes = Vec::new();
+    for node in scene.nodes.iter() {
+        let mut gltf_node = Map::new();
+        if !node.name.is_empty() {
+            gltf_node.insert("name".to_string(), json!(node.name));
+        }
+        if !node.children.is_empty() {
+            gltf_node.insert("children".to_string(), json!(node.children));
+        }
+        if node.transformation != Matrix4::identity() {
+            let matrix: &[f32; 16] = node.transformation.as_ref();
+            gltf_node.insert("matrix".to_string(), json!(matrix.to_vec()));
+        }
+        let node_primitives: Vec<Value> = node.meshes.iter()
+            .flat_map(|x| primitives[*x].iter().cloned())
+            .collect();
+        if !node_primitives.is_empty() {
+            let mut mesh = json!({ "primitives": node_primitives });
+            if let [index] = node.meshes[..] {
+                if !scene.meshes[index].name.is_empty() {
+                    mesh["name"] = json!(scene.meshes[index].name);
+                }
+            }
+            meshes.push(mesh);
+            gltf_node.insert("mesh".to_string(), json!(meshes.len() - 1));
+        }
+        nodes.push(Value::Object(gltf_node));
+    }
+
+    let mut images: Vec<String> = Vec::new();
+    let materials: Vec<Value> = scene.materials.iter()
+        .map(|x| material(x, &mut images))
+        .collect();
+    let roots = scene.roots();
+
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "obj_wasm" },
+    });
+    let collections = [
+        ("nodes", nodes),
+        ("meshes", meshes),
+        ("materials", materials),
+        ("textures", (0..images.len()).map(|x| json!({ "source": x })).collect()),
+        ("images", images.iter().map(|x| json!({ "uri": encode_uri(x) })).collect()),
+        ("accessors", buffers.accessors),
+        ("bufferViews", buffers.views),
+    ];
+    for (name, values) in collections.iter() {
+        if !values.is_empty() {
+            document[*name] = json!(values);
+        }
+    }
+    if !scene.nodes.is_empty() {
+        document["scene"] = json!(0);
+        document["scenes"] = json!([{ "nodes": roots }]);
+    }
+    if !buffers.bin.is_empty() {
+        document["buffers"] = json!([{ "byteLength": buffers.bin.len() }]);
+    }
+    (document, buffers.bin)
+}
+
+/// Write the vertex data of the mesh, and return a primitive for each kind of primitive it draws.
+fn mesh_primitives(mesh: &SceneMesh, buffers: &mut Buffers) -> Vec<Value> {
+    let count = mesh.gv.len() / 3;
+    let lines: Vec<usize> = mesh.line_strips.iter()
+        .flat_map(|x| mesh.face_indices[x.clone()].windows(2).flatten().cloned().collect::<Vec<_>>())
+        .collect();
+    let kinds = [
+        (TRIANGLES, mesh.face_indices[mesh.triangles.clone()].to_vec()),
+        (LINES, lines),
+        (POINTS, mesh.face_indices[mesh.points.clone()].to_vec()),
+    ];
+    if count == 0 || kinds.iter().all(|(_, indices)| indices.is_empty()) {
+        return Vec::new();
+    }
+
+    let mut attributes = Map::new();
+    attributes.insert("POSITION".to_string(), json!(buffers.push_floats(&mesh.gv, 3, "VEC3", true)));
+    if mesh.vn.len() == count * 3 {
+        let normals: Vec<f32> = mesh.vn.chunks(3).flat_map(unit_normal).collect();
+        attributes.insert("NORMAL".to_string(), json!(buffers.push_floats(&normals, 3, "VEC3", false)));
+    }
+    if mesh.tangents.len() == count * 4 && mesh.vn.len() == count * 3 {
+        let tangents = buffers.push_floats(&mesh.tangents, 4, "VEC4", false);
+        attributes.insert("TANGENT".to_string(), json!(tangents));
+    }
+    if mesh.vt.len() == count * 2 {
+        // glTF puts the origin of the textures at the top left, .obj at the bottom left.
+        let uvs: Vec<f32> = mesh.vt.chunks(2).flat_map(|x| vec![x[0], 1.0 - x[1]]).collect();
+        attributes.insert("TEXCOORD_0".to_string(), json!(buffers.push_floats(&uvs, 2, "VEC2", false)));
+    }
+    if mesh.colors.len() == count * 4 {
+        attributes.insert("COLOR_0".to_string(), json!(buffers.push_floats(&mesh.colors, 4, "VEC4", false)));
+    }
+
+    let mut primitives = Vec::new();
+    for (mode, indices) in kinds.iter().filter(|(_, indices)| !indices.is_empty()) {
+        let mut primitive = json!({
+            "attributes": attributes,
+            "indices": buffers.push_indices(indices),
+            "mode": mode,
+        });
+        if let Some(material) = &mesh.material {
+            primitive["material"] = json!(material.index);
+        }
+        primitives.push(primitive);
+    }
+    primitives
+}
+
+/// Map the Phong material onto the metallic-roughness model.
+fn material(material: &Material, images: &mut Vec<String>) -> Value {
+    let mut texture = |map: &Option<TextureMap>| -> Option<Value> {
+        let path = &map.as_ref()?.path;
+        let index = match images.iter().position(|x| x == path) {
+            Some(index) => index,
+            None => {
+                images.push(path.clone());
+                images.len() - 1
+            }
+        };
+        Some(json!({ "index": index }))
+    };
+    let (metallic, roughness) = match &material.pbr {
+        Some(pbr) => (pbr.metallic, pbr.roughness),
+        // The usual conversion of the specular exponent into roughness.
+        None => (0.0, (2.0 / (material.shininess.max(0.0) + 2.0)).sqrt()),
+    };
+    let d = material.diffuse;
+    let alpha = clamp(material.dissolve);
+    let mut pbr = json!({
+        "baseColorFactor": [clamp(d[0]), clamp(d[1]), clamp(d[2]), alpha],
+        "metallicFactor": clamp(metallic),
+        "roughnessFactor": clamp(roughness),
+    });
+    if let Some(texture) = texture(&material.texture_diffuse) {
+        pbr["baseColorTexture"] = texture;
+    }
+    let mut gltf_material = json!({ "pbrMetallicRoughness": pbr });
+    if !material.name.is_empty() {
+        gltf_material["name"] = json!(material.name);
+    }
+    if let Some(texture) = texture(&material.texture_normal) {
+        gltf_material["normalTexture"] = texture;
+    }
+    let e = material.emissive;
+    if e.iter().any(|x| *x > 0.0) {
+        gltf_material["emissiveFactor"] = json!([clamp(e[0]), clamp(e[1]), clamp(e[2])]);
+    }
+    if let Some(texture) = texture(&material.texture_emissive) {
+        gltf_material["emissiveTexture"] = texture;
+        if e.iter().all(|x| *x <= 0.0) {
+            gltf_material["emissiveFactor"] = json!([1.0, 1.0, 1.0]);
+        }
+    }
+    if alpha < 1.0 {
+        gltf_material["alphaMode"] = json!("BLEND");
+    }
+    gltf_material
+}
+
+/// Unit length normal, as glTF requires, pointing up when the normal is missing.
+fn unit_normal(normal: &[f32]) -> Vec<f32> {
+    let n = Vector3::new(normal[0], normal[1], normal[2]);
+    let n = if n.magnitude2() > 0.0 { n.normalize() } else { Vector3::unit_z() };
+    vec![n.x, n.y, n.z]
+}
+
+fn clamp(value: f32) -> f32 {
+    value.clamp(0.0, 1.0)
+}
+
+/// Pad the data to the next multiple of four bytes.
+fn pad(data: &mut Vec<u8>, value: u8) {
+    while !data.len().is_multiple_of(4) {
+        data.push(value);
+    }
+}
+
+/// Escape the characters of the path that aren't allowed in a URI.
+fn encode_uri(path: &str) -> String {
+    let mut uri = String::new();
+    for byte in path.replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+            | b'-' | b'_' | b'.' | b'~' | b'/' | b':' | b'%' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
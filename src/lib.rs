@@ -1,14 +1,32 @@
 #![allow(unused)]
+#[cfg(feature = "web_sys")]
 pub mod utils;
 pub mod reader;
 pub mod data;
 pub mod parser;
 pub mod scene;
 pub mod mtlreader;
+pub mod error;
+pub mod resolver;
+pub mod streaming;
+pub mod normals;
+pub mod tangents;
+pub mod triangulate;
+pub mod options;
+pub mod traversal;
+pub mod bounds;
+pub mod normalize;
+pub mod serialize;
+pub mod gltf;
+pub mod writer;
+#[cfg(feature = "web_sys")]
+pub mod loader;
+#[cfg(feature = "web_sys")]
+pub mod bindings;
 
+#[cfg(feature = "web_sys")]
 use wasm_bindgen::prelude::*;
 use reader::ObjReader;
-use web_sys::*;
 use data::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -18,33 +36,31 @@ use data::*;
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 /// Log for easier debugging and displaying in web-console.
+#[cfg(feature = "web_sys")]
 #[macro_export]
 macro_rules! log {
     ( $( $t:tt )* ) => {
-        web_sys::console::log_1(&format!( $( $t )* ).into());
+        web_sys::console::log_1(&format!( $( $t )* ).into())
     }
 }
 
 
+#[cfg(feature = "web_sys")]
 #[wasm_bindgen]
-pub fn load_scene(data: &str) {
+pub fn load_scene(data: &str) -> Result<bindings::JsSceneGraph, JsValue> {
     utils::set_panic_hook();
-    let reader = ObjReader::new().unwrap();
-    let face = Face::new();
-    let material = Material::new();
-    let mesh = Mesh::new();
-    let model = Model::new();
-    let object = Object::new();
-
-    log!("{:?}", reader.scene);
-    log!("This should load the default object file");
+    let mut reader = ObjReader::new()?;
+    reader.read(data, "")?;
+    Ok(bindings::JsSceneGraph::new(reader.scene))
 }
 
+#[cfg(feature = "web_sys")]
 #[wasm_bindgen]
 pub fn load_material(data: &str) {
     log!("Loading the material data below:\n {}", data)
 }
 
+#[cfg(feature = "web_sys")]
 #[wasm_bindgen]
 pub fn greet() {
     utils::set_panic_hook();
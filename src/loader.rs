@@ -0,0 +1,82 @@
+use futures::stream::StreamExt;
+use js_sys::Uint8Array;
+use wasm_bindgen::{prelude::*, JsCast};
+
+use crate::bindings::JsSceneGraph;
+use crate::options::LoadOptions;
+use crate::reader::ObjReader;
+use crate::resolver::MemoryResolver;
+use crate::streaming::StreamingParser;
+
+/// Allows external js to load the information object file.
+#[wasm_bindgen]
+pub struct DataLoader {
+    reader: ObjReader,
+    mat_data: String,
+    scene_data: String,
+    resources: MemoryResolver,
+}
+
+#[wasm_bindgen]
+impl DataLoader {
+    pub fn new() -> Result<DataLoader, JsValue> {
+        let loader =  DataLoader {
+            reader: ObjReader::new()?,
+            mat_data: String::new(),
+            scene_data: String::new(),
+            resources: MemoryResolver::new(),
+        };
+        Ok(loader)
+    }
+
+    /// Use the options for the next loads.
+    pub fn set_options(&mut self, options: LoadOptions) {
+        self.reader.options = options;
+    }
+
+    pub fn load_scene(&mut self, data: String) {
+        self.scene_data = data;
+    }
+
+    pub fn load_material(&mut self, data: String) {
+        self.mat_data = data;
+    }
+
+    pub fn load(&mut self) -> Result<(), JsValue> {
+        self.reader.read(&self.scene_data, &self.mat_data)?;
+        Ok(())
+    }
+
+    /// Take the scene loaded so far, leaving the loader empty for the next loads.
+    pub fn scene(&mut self) -> JsSceneGraph {
+        JsSceneGraph::new(std::mem::take(&mut self.reader.scene))
+    }
+
+    /// Add the content of a file that the scene refers to, such as a material library.
+    pub fn add_resource(&mut self, path: String, data: String) {
+        self.resources.insert(&path, &data);
+    }
+
+    /// Load the file at `path` along with the material libraries it refers to
+    /// from the added resources.
+    pub fn load_path(&mut self, path: String) -> Result<(), JsValue> {
+        self.reader.read_with(&path, &self.resources)?;
+        Ok(())
+    }
+
+    /// Parse the file at `path` from the stream, such as the body of a fetch response,
+    /// while its data arrives. The material libraries come from the added resources.
+    pub async fn load_stream(mut self, path: String, stream: web_sys::ReadableStream)
+                             -> Result<DataLoader, JsValue> {
+        let mut chunks = wasm_streams::ReadableStream::from_raw(stream.unchecked_into())
+            .into_stream();
+        let options = self.reader.options.clone();
+        let mut parser = StreamingParser::with_options(&path, options, &self.resources);
+        while let Some(chunk) = chunks.next().await {
+            parser.feed(&Uint8Array::new(&chunk?).to_vec())?;
+        }
+        let parser = parser.finish()?;
+        self.reader.read_model(&parser.model.borrow())?;
+        Ok(self)
+    }
+}
@@ -1,125 +1,351 @@
 use crate::data::*;
-use crate::log;
+use crate::error::{Location, ObjError};
+use crate::parser::rest_of_line;
+use crate::resolver::ResourceResolver;
 
 use std::cell::RefCell;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::str::{FromStr, SplitWhitespace};
-use wasm_bindgen::prelude::JsValue;
 
-/// Structure to read the current active material and update the material library.
+/// Structure to read the materials into the material library.
 pub struct MtlReader<'mat> {
-    cur_material: &'mat mut Rc<RefCell<Material>>,
+    /// Material the statements apply to, the last one declared by `newmtl`.
+    cur_material: Rc<RefCell<Material>>,
     material_info: &'mat mut HashMap<String, Rc<RefCell<Material>>>,
+    /// Name of the file being parsed, used to report errors.
+    file_name: &'mat str,
+    /// Line that is being parsed.
+    line: usize,
+    /// Resolves the textures relative to the file.
+    resolver: &'mat dyn ResourceResolver,
+    /// Stop at the first malformed statement, instead of recording it in `warnings`.
+    strict: bool,
+    /// Errors of the statements that were skipped.
+    warnings: &'mat mut Vec<ObjError>,
 }
 
 impl<'mat> MtlReader<'mat> {
-    /// Load the material reader from active material and material library using the model.
+    /// Read the materials into the library, leaving the active material of the model as it is.
+    /// Statements before the first `newmtl` don't belong to any material and are dropped.
+    /// Unless `strict`, the malformed statements are skipped and their errors added to `warnings`.
     pub fn load(
-        material: &mut (Rc<RefCell<Material>>, HashMap<String, Rc<RefCell<Material>>>),
+        library: &mut HashMap<String, Rc<RefCell<Material>>>,
+        file_name: &str,
         data: &str,
-    ) -> Result<(), JsValue> {
+        resolver: &dyn ResourceResolver,
+        strict: bool,
+        warnings: &mut Vec<ObjError>,
+    ) -> Result<(), ObjError> {
         let mut reader = MtlReader {
-            cur_material: &mut material.0,
-            material_info: &mut material.1,
+            cur_material: Rc::new(RefCell::new(Material::new())),
+            material_info: library,
+            file_name,
+            line: 0,
+            resolver,
+            strict,
+            warnings,
         };
         reader.parse_mtl(data)?;
         Ok(())
     }
 
     /// Parses .mtl file
-    /// TODO: Allow parsing textures strings.
-    pub fn parse_mtl(&mut self, data: &str) -> Result<(), JsValue> {
-        for line in data.lines() {
-            let mut words = line[..].split_whitespace();
-            match words.next() {
-                Some("#") | None => continue,
-                Some("newmtl") => {
-                    let name: String = words.map(|x| "".to_owned() + x).collect();
-                    self.add_material(name)?;
-                },
-                Some("Ka") => {
-                    let ambient =
-                    self.parse_floats(words, &mut self.cur_material.borrow_mut().ambient)?;
-                },
-                Some("Kd") => {
-                    self.parse_floats(words, &mut self.cur_material.borrow_mut().diffuse)?;
-                },
-                Some("Ks") => {
-                    self.parse_floats(words, &mut self.cur_material.borrow_mut().specular)?;
-                },
-                Some("Tf") => {
-                    self.parse_floats(words, &mut self.cur_material.borrow_mut().transmission)?;
-                },
-                Some("Ni") => {
-                    self.parse_single(words, &mut self.cur_material.borrow_mut().ri)?;
-                },
-                Some("Ns") => {
-                    self.parse_single(words, &mut self.cur_material.borrow_mut().shininess)?;
-                },
-                Some("illum") => {
-                    if let Some(p) = words.next() {
-                        match FromStr::from_str(p) {
-                            Ok(x) => self.cur_material.borrow_mut().illumination = Some(x),
-                            Err(_) => return Err(JsValue::from_str("Fetch error")),
-                        }
-                    } else {
-                        return Err(JsValue::from_str("Fetch error"));
-                    }
-                },
-                Some(_) => {}
+    /// Unless the reading is strict, a malformed statement is skipped with a warning.
+    pub fn parse_mtl(&mut self, data: &str) -> Result<(), ObjError> {
+        for (number, line) in data.lines().enumerate() {
+            self.line = number + 1;
+            match self.read_statement(line) {
+                Err(error) if !self.strict => self.warnings.push(error),
+                result => result?,
             }
         }
         Ok(())
     }
 
-    /// Adds the materials into the libary and sets it as active material.
-    fn add_material(&mut self, name: String) -> Result<(), JsValue> {
+    /// Read the statement on the line into the current material.
+    fn read_statement(&mut self, line: &str) -> Result<(), ObjError> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("#") | None => {},
+            Some("newmtl") => {
+                self.add_material(rest_of_line(line, words).to_string())?;
+            },
+            Some("Ka") => {
+                self.parse_floats(line, words, &mut self.cur_material.borrow_mut().ambient)?;
+            },
+            Some("Kd") => {
+                self.parse_floats(line, words, &mut self.cur_material.borrow_mut().diffuse)?;
+            },
+            Some("Ks") => {
+                self.parse_floats(line, words, &mut self.cur_material.borrow_mut().specular)?;
+            },
+            Some("Tf") => {
+                self.parse_floats(line, words, &mut self.cur_material.borrow_mut().transmission)?;
+            },
+            Some("Ke") => {
+                self.parse_floats(line, words, &mut self.cur_material.borrow_mut().emissive)?;
+            },
+            Some("d") => {
+                let mut words = words.peekable();
+                let halo = words.peek() == Some(&"-halo");
+                if halo {
+                    words.next();
+                }
+                match words.next() {
+                    Some(p) => {
+                        let mut material = self.cur_material.borrow_mut();
+                        material.dissolve = self.parse_float(line, p)?;
+                        material.dissolve_halo = halo;
+                    },
+                    None => return Err(self.malformed(line, line.trim())),
+                }
+            },
+            Some("Tr") => {
+                let mut transparency = 0.0;
+                self.parse_single(line, words, &mut transparency)?;
+                self.cur_material.borrow_mut().dissolve = 1.0 - transparency;
+            },
+            Some("Ni") => {
+                self.parse_single(line, words, &mut self.cur_material.borrow_mut().ri)?;
+            },
+            Some("Ns") => {
+                self.parse_single(line, words, &mut self.cur_material.borrow_mut().shininess)?;
+            },
+            Some("illum") => {
+                match words.next() {
+                    Some(p) => match FromStr::from_str(p) {
+                        Ok(x) => self.cur_material.borrow_mut().illumination = Some(x),
+                        Err(_) => return Err(self.malformed(line, p)),
+                    },
+                    None => return Err(self.malformed(line, line.trim())),
+                }
+            },
+            Some("map_Ka") => {
+                self.cur_material.borrow_mut().texture_ambient = Some(self.parse_texture(line, words)?);
+            },
+            Some("map_Kd") => {
+                self.cur_material.borrow_mut().texture_diffuse = Some(self.parse_texture(line, words)?);
+            },
+            Some("map_Ks") => {
+                self.cur_material.borrow_mut().texture_specular = Some(self.parse_texture(line, words)?);
+            },
+            Some("map_Ke") => {
+                self.cur_material.borrow_mut().texture_emissive = Some(self.parse_texture(line, words)?);
+            },
+            Some("map_Ns") => {
+                self.cur_material.borrow_mut().texture_shininess = Some(self.parse_texture(line, words)?);
+            },
+            Some("map_d") => {
+                self.cur_material.borrow_mut().texture_dissolve = Some(self.parse_texture(line, words)?);
+            },
+            Some("bump") | Some("map_bump") | Some("map_Bump") => {
+                self.cur_material.borrow_mut().texture_bump = Some(self.parse_texture(line, words)?);
+            },
+            Some("norm") => {
+                self.cur_material.borrow_mut().texture_normal = Some(self.parse_texture(line, words)?);
+            },
+            Some("disp") => {
+                self.cur_material.borrow_mut().texture_displacement = Some(self.parse_texture(line, words)?);
+            },
+            Some("decal") => {
+                self.cur_material.borrow_mut().texture_decal = Some(self.parse_texture(line, words)?);
+            },
+            Some(keyword @ ("Pr" | "Pm" | "Ps" | "Pc" | "Pcr" | "aniso" | "anisor")) => {
+                let mut material = self.cur_material.borrow_mut();
+                let pbr = material.pbr.get_or_insert_with(PbrMaterial::new);
+                let value = match keyword {
+                    "Pr" => &mut pbr.roughness,
+                    "Pm" => &mut pbr.metallic,
+                    "Ps" => &mut pbr.sheen,
+                    "Pc" => &mut pbr.clearcoat_thickness,
+                    "Pcr" => &mut pbr.clearcoat_roughness,
+                    "aniso" => &mut pbr.anisotropy,
+                    _ => &mut pbr.anisotropy_rotation,
+                };
+                self.parse_single(line, words, value)?;
+            },
+            Some(keyword @ ("map_Pr" | "map_Pm" | "map_Ps" | "map_RMA")) => {
+                let texture = Some(self.parse_texture(line, words)?);
+                let mut material = self.cur_material.borrow_mut();
+                let pbr = material.pbr.get_or_insert_with(PbrMaterial::new);
+                match keyword {
+                    "map_Pr" => pbr.texture_roughness = texture,
+                    "map_Pm" => pbr.texture_metallic = texture,
+                    "map_Ps" => pbr.texture_sheen = texture,
+                    _ => pbr.texture_rma = texture,
+                }
+            },
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Locate the token within the line that is being parsed.
+    fn location(&self, line: &str, token: &str) -> Location {
+        Location::new(self.file_name, self.line, line, token)
+    }
+
+    /// Error for statements that are missing components or have too many of them.
+    /// The `token` has to be a slice of the `line` to report the column.
+    fn malformed(&self, line: &str, token: &str) -> ObjError {
+        ObjError::MalformedStatement {
+            location: self.location(line, token),
+            token: token.to_string(),
+        }
+    }
+
+    /// Adds the materials into the libary and sets it as the material being read.
+    fn add_material(&mut self, name: String) -> Result<(), ObjError> {
         let material = match self.material_info.entry(name.clone()) {
-            Entry::Occupied(o) => self.material_info.get(&name).unwrap(),
+            Entry::Occupied(o) => o.into_mut(),
             Entry::Vacant(v) => v.insert(Rc::new(RefCell::new(Material::new()))),
         };
-        material.borrow_mut().name = name.clone();
-        *self.cur_material =  Rc::clone(material);
+        material.borrow_mut().name = name;
+        self.cur_material = Rc::clone(material);
         Ok(())
     }
 
+    /// Parse a float from the token.
+    fn parse_float(&self, line: &str, p: &str) -> Result<f32, ObjError> {
+        FromStr::from_str(p).map_err(|_| ObjError::BadFloat {
+            location: self.location(line, p),
+            token: p.to_string(),
+        })
+    }
+
     /// Parses array of floats.
-    fn parse_floats(&self, words: SplitWhitespace, vals: &mut [f32; 3])
-                        -> Result<(), JsValue> {
+    fn parse_floats(&self, line: &str, words: SplitWhitespace, vals: &mut [f32; 3])
+                        -> Result<(), ObjError> {
         let count = words.clone().count();
-        if count == 1 {
+        if count == 0 {
+            return Err(self.malformed(line, line.trim()));
+        } else if count == 1 {
             for p in words {
-                match FromStr::from_str(p) {
-                    Ok(x) => vals[0] = x,
-                    Err(e) => return Err(JsValue::from_str("Fetch error")),
-                }
+                vals[0] = self.parse_float(line, p)?;
             }
             vals[1] = 0.0;
             vals[2] = 0.0;
         } else {
             for (i, p) in words.enumerate().take(3) {
-                match FromStr::from_str(p) {
-                    Ok(x) => vals[i] = x,
-                    Err(e) => return Err(JsValue::from_str("Fetch error")),
-                }
+                vals[i] = self.parse_float(line, p)?;
             }
         }
         Ok(())
     }
 
     /// Parse a single value information
-    fn parse_single(&self, words: SplitWhitespace, vals: &mut f32)
-                        -> Result<(), JsValue>
+    fn parse_single(&self, line: &str, words: SplitWhitespace, vals: &mut f32)
+                        -> Result<(), ObjError>
     {
         for p in words {
-            match FromStr::from_str(p){
-                Ok(x) => *vals = x,
-                Err(e) => return Err(JsValue::from_str("Fetch error")),
-            }
+            *vals = self.parse_float(line, p)?;
         }
         Ok(())
     }
-    //TODO: Create parsing of string information for textures.
+
+    /// Parse the texture options followed by the name of the texture file,
+    /// which may contain spaces. The name is kept as written, and also resolved relative to the file.
+    fn parse_texture(&self, line: &str, words: SplitWhitespace) -> Result<TextureMap, ObjError> {
+        let tokens: Vec<&str> = words.collect();
+        let mut map = TextureMap::new("");
+        let mut i = 0;
+        while i < tokens.len() && tokens[i].starts_with('-') {
+            let option = tokens[i];
+            i += 1;
+            match option {
+                "-o" | "-s" | "-t" => {
+                    let vals = self.parse_option_floats(line, &tokens, &mut i, 3)?;
+                    let target = match option {
+                        "-o" => &mut map.offset,
+                        "-s" => &mut map.scale,
+                        _ => &mut map.turbulence,
+                    };
+                    target[..vals.len()].copy_from_slice(&vals);
+                },
+                "-bm" => map.bump_multiplier = self.parse_option_floats(line, &tokens, &mut i, 1)?[0],
+                "-boost" => map.boost = Some(self.parse_option_floats(line, &tokens, &mut i, 1)?[0]),
+                "-mm" => {
+                    let vals = self.parse_option_floats(line, &tokens, &mut i, 2)?;
+                    map.base = vals[0];
+                    if let Some(gain) = vals.get(1) {
+                        map.gain = *gain;
+                    }
+                },
+                "-clamp" => map.clamp = self.parse_option_flag(line, option, &tokens, &mut i)?,
+                "-blendu" => map.blend_u = self.parse_option_flag(line, option, &tokens, &mut i)?,
+                "-blendv" => map.blend_v = self.parse_option_flag(line, option, &tokens, &mut i)?,
+                "-cc" => map.color_correction = self.parse_option_flag(line, option, &tokens, &mut i)?,
+                "-imfchan" => {
+                    map.channel = Some(match tokens.get(i) {
+                        Some(&"r") => TextureChannel::Red,
+                        Some(&"g") => TextureChannel::Green,
+                        Some(&"b") => TextureChannel::Blue,
+                        Some(&"m") => TextureChannel::Matte,
+                        Some(&"l") => TextureChannel::Luminance,
+                        Some(&"z") => TextureChannel::Depth,
+                        Some(x) => return Err(self.malformed(line, x)),
+                        None => return Err(self.malformed(line, option)),
+                    });
+                    i += 1;
+                },
+                "-texres" => {
+                    match tokens.get(i).map(|x| (x, u32::from_str(x))) {
+                        Some((_, Ok(x))) => map.resolution = Some(x),
+                        Some((x, Err(_))) => return Err(self.malformed(line, x)),
+                        None => return Err(self.malformed(line, option)),
+                    }
+                    i += 1;
+                },
+                _ => return Err(self.malformed(line, option)),
+            }
+        }
+        match tokens.get(i) {
+            Some(first) => {
+                let start = first.as_ptr() as usize - line.as_ptr() as usize;
+                map.path = line[start..].trim_end().to_string();
+                map.resolved = self.resolver.resolve(self.file_name, &map.path);
+                Ok(map)
+            },
+            None => Err(self.malformed(line, line.trim())),
+        }
+    }
+
+    /// Parse at least one and up to `max` floats for the texture option.
+    /// The last token is always left for the name of the file.
+    fn parse_option_floats(&self, line: &str, tokens: &[&str], i: &mut usize, max: usize)
+                           -> Result<Vec<f32>, ObjError> {
+        let mut vals = Vec::new();
+        while vals.len() < max && *i + 1 < tokens.len() {
+            match f32::from_str(tokens[*i]) {
+                Ok(x) => vals.push(x),
+                Err(_) => break,
+            }
+            *i += 1;
+        }
+        if vals.is_empty() {
+            return match tokens.get(*i) {
+                Some(x) => Err(ObjError::BadFloat {
+                    location: self.location(line, x),
+                    token: x.to_string(),
+                }),
+                None => Err(self.malformed(line, line.trim())),
+            };
+        }
+        Ok(vals)
+    }
+
+    /// Parse the `on` or `off` value of the texture option.
+    fn parse_option_flag(&self, line: &str, option: &str, tokens: &[&str], i: &mut usize)
+                         -> Result<bool, ObjError> {
+        let flag = match tokens.get(*i) {
+            Some(&"on") => true,
+            Some(&"off") => false,
+            Some(x) => return Err(self.malformed(line, x)),
+            None => return Err(self.malformed(line, option)),
+        };
+        *i += 1;
+        Ok(flag)
+    }
 }
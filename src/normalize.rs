@@ -0,0 +1,78 @@
+//! Fits the scene into a unit volume, whatever the units and origin it was modelled in.
+use std::collections::HashSet;
+
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+
+use crate::bounds::transform_point;
+use crate::scene::SceneGraph;
+
+/// Volume the scene is fitted into, centered at the origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FitVolume {
+    /// Cube with sides of length 1, fitted around the box along the axes.
+    Cube,
+    /// Sphere of radius 1, fitted around the bounding sphere.
+    Sphere,
+}
+
+/// Where the normalization is applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    /// Change the positions of the meshes.
+    Bake,
+    /// Prepend the change to the transformation of the root nodes.
+    Transform,
+}
+
+/// Recenter and uniformly scale the scene into the volume.
+/// Returns the matrix applied in world space, whose inverse undoes the change.
+/// Empty scenes are left as they are.
+pub fn normalize(scene: &mut SceneGraph, volume: FitVolume, mode: Normalization) -> Matrix4<f32> {
+    let bounds = scene.bounds();
+    if bounds.aabb.is_empty() {
+        return Matrix4::identity();
+    }
+    let (center, size) = match volume {
+        FitVolume::Cube => {
+            let size = bounds.aabb.size();
+            (bounds.aabb.center(), size.x.max(size.y).max(size.z))
+        },
+        FitVolume::Sphere => (bounds.sphere.center, bounds.sphere.radius),
+    };
+    let scale = if size > 0.0 { 1.0 / size } else { 1.0 };
+    let matrix = Matrix4::from_scale(scale) * Matrix4::from_translation(-center);
+    match mode {
+        Normalization::Transform => {
+            for root in scene.roots() {
+                let node = &mut scene.nodes[root];
+                node.transformation = matrix * node.transformation;
+            }
+        },
+        Normalization::Bake => bake(scene, &matrix),
+    }
+    matrix
+}
+
+/// Apply the world space matrix to the positions of every mesh, within the space of its node.
+/// The scale is uniform, so the normals and tangents keep their directions.
+fn bake(scene: &mut SceneGraph, matrix: &Matrix4<f32>) {
+    let mut changes: Vec<(usize, Matrix4<f32>)> = Vec::new();
+    let mut baked: HashSet<usize> = HashSet::new();
+    for visit in scene.depth_first() {
+        let local = match visit.world.invert() {
+            Some(inverse) => inverse * matrix * visit.world,
+            None => *matrix,
+        };
+        for mesh in visit.node.meshes.iter() {
+            if baked.insert(*mesh) {
+                changes.push((*mesh, local));
+            }
+        }
+    }
+    for (mesh, local) in changes {
+        for position in scene.meshes[mesh].gv.chunks_mut(3) {
+            let p = transform_point(&local, Vector3::new(position[0], position[1], position[2]));
+            position.copy_from_slice(&[p.x, p.y, p.z]);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Generates the normals for the faces that are read without them.
+use std::collections::HashMap;
+
+#[cfg(feature = "web_sys")]
+use wasm_bindgen::prelude::*;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::data::*;
+use crate::triangulate::{face_triangles, polygon_normal};
+
+/// Decides how much each face contributes to the smoothed normal of its vertices.
+#[cfg_attr(feature = "web_sys", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalWeighting {
+    /// Larger faces contribute more.
+    Area,
+    /// Faces contribute by the angle of their corner at the vertex.
+    Angle,
+}
+
+/// Holds the normals of the positions, smoothed within each smoothing group.
+pub struct SmoothNormals {
+    normals: HashMap<(usize, u32), Vector3<f32>>,
+}
+
+impl SmoothNormals {
+    /// Accumulate the normals of the smoothed faces without normals in the model.
+    pub fn new(model: &Model, weighting: NormalWeighting) -> Self {
+        let mut normals: HashMap<(usize, u32), Vector3<f32>> = HashMap::new();
+        let faces = model.meshes.iter().flat_map(|mesh| mesh.faces.iter())
+            .filter(|face| needs_normals(face) && face.smoothing_group != 0);
+        for face in faces {
+            for corners in face_triangles(model, face) {
+                let triangle = corners.map(|x| face.vertices[x]);
+                let normal = triangle_normal(model, &triangle);
+                for corner in 0..3 {
+                    let weight = match weighting {
+                        NormalWeighting::Area => normal,
+                        NormalWeighting::Angle => {
+                            let angle = corner_angle(model, &triangle, corner);
+                            normalize(normal) * angle
+                        },
+                    };
+                    *normals.entry((triangle[corner], face.smoothing_group))
+                        .or_insert_with(|| Vector3::new(0.0, 0.0, 0.0)) += weight;
+                }
+            }
+        }
+        for normal in normals.values_mut() {
+            *normal = normalize(*normal);
+        }
+        SmoothNormals { normals }
+    }
+
+    /// Normal of the position within the smoothing group.
+    pub fn get(&self, position: usize, group: u32) -> [f32; 3] {
+        match self.normals.get(&(position, group)) {
+            Some(normal) => (*normal).into(),
+            None => [0.0; 3],
+        }
+    }
+}
+
+/// True, if the face is a surface that was read without normals.
+pub fn needs_normals(face: &Face) -> bool {
+    face.normals.is_empty() && face.face_type != FaceType::Point && face.face_type != FaceType::Line
+}
+
+/// Normal of the triangle, not normalized so its length is twice the area.
+pub fn triangle_normal(model: &Model, triangle: &[usize]) -> Vector3<f32> {
+    let a = position(model, triangle[0]);
+    let b = position(model, triangle[1]);
+    let c = position(model, triangle[2]);
+    (b - a).cross(c - a)
+}
+
+/// Normal of the triangle or polygon for the faces that aren't smoothed.
+pub fn flat_normal(model: &Model, polygon: &[usize]) -> [f32; 3] {
+    let points: Vec<Vector3<f32>> = polygon.iter().map(|x| position(model, *x)).collect();
+    normalize(polygon_normal(&points)).into()
+}
+
+/// Angle of the triangle at the given corner, in radians.
+fn corner_angle(model: &Model, triangle: &[usize], corner: usize) -> f32 {
+    let a = position(model, triangle[corner]);
+    let b = position(model, triangle[(corner + 1) % 3]);
+    let c = position(model, triangle[(corner + 2) % 3]);
+    let (u, v) = (b - a, c - a);
+    if u.magnitude2() == 0.0 || v.magnitude2() == 0.0 {
+        return 0.0;
+    }
+    u.angle(v).0
+}
+
+/// Normalize the vector, leaving the degenerate ones at zero.
+fn normalize(normal: Vector3<f32>) -> Vector3<f32> {
+    let length = normal.magnitude();
+    if length > 0.0 {
+        normal / length
+    } else {
+        normal
+    }
+}
+
+fn position(model: &Model, index: usize) -> Vector3<f32> {
+    Vector3::new(model.gv[index * 3], model.gv[index * 3 + 1], model.gv[index * 3 + 2])
+}
@@ -0,0 +1,71 @@
+//! Options controlling how the files are parsed and turned into the scene.
+#[cfg(feature = "web_sys")]
+use wasm_bindgen::prelude::*;
+
+use crate::data::Material;
+use crate::normals::NormalWeighting;
+
+/// Controls the behavior of `Parser`, `ObjReader` and `DataLoader`.
+/// The same files can be loaded with different settings by different pipelines.
+#[cfg_attr(feature = "web_sys", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    /// Split the faces with more than three corners into triangles.
+    /// Otherwise they are kept as polygons, and only `face_indices` holds their triangles.
+    pub triangulate: bool,
+    /// Merge the identical corners into shared vertices instead of copying every corner,
+    /// so a single index addresses the position, texture and normal.
+    pub indexed: bool,
+    /// Skip the point statements.
+    pub ignore_points: bool,
+    /// Skip the line statements.
+    pub ignore_lines: bool,
+    /// Stop at the first malformed statement. Otherwise the statement is skipped
+    /// and its error is recorded as a warning.
+    pub strict: bool,
+    /// Generate the normals of the faces read without them.
+    pub generate_normals: bool,
+    /// How the faces contribute to the generated normals of the smoothed vertices.
+    pub normal_weighting: NormalWeighting,
+    /// Generate the tangents of the meshes that have textures and normals.
+    pub generate_tangents: bool,
+    /// Material for the faces without a known material, instead of the light grey one.
+    /// From javascript, it is set by `set_default_material`.
+    #[cfg_attr(feature = "web_sys", wasm_bindgen(skip))]
+    pub default_material: Option<Material>,
+}
+
+#[cfg_attr(feature = "web_sys", wasm_bindgen)]
+impl LoadOptions {
+    /// Default options: triangulated, expanded corners, strict, with generated normals.
+    #[cfg_attr(feature = "web_sys", wasm_bindgen(constructor))]
+    pub fn new() -> LoadOptions {
+        LoadOptions {
+            triangulate: true,
+            indexed: false,
+            ignore_points: false,
+            ignore_lines: false,
+            strict: true,
+            generate_normals: true,
+            normal_weighting: NormalWeighting::Area,
+            generate_tangents: false,
+            default_material: None,
+        }
+    }
+
+    /// Use a material with the name and diffuse color for the faces without a known material.
+    pub fn set_default_material(&mut self, name: &str, diffuse: &[f32]) {
+        let mut material = Material::new();
+        material.name = name.to_string();
+        for (value, component) in material.diffuse.iter_mut().zip(diffuse) {
+            *value = *component;
+        }
+        self.default_material = Some(material);
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions::new()
+    }
+}
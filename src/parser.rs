@@ -1,230 +1,364 @@
 use std::cell::RefCell;
-use std::fmt::Error;
+use std::io;
 use std::rc::Rc;
 use std::str::{FromStr, SplitWhitespace};
-use std::collections::HashMap;
-use wasm_bindgen::prelude::JsValue;
-use std::collections::hash_map::Entry;
 
 use crate::data::*;
-use crate::log;
+use crate::error::{Location, ObjError};
 use crate::mtlreader::MtlReader;
+use crate::options::LoadOptions;
+use crate::resolver::ResourceResolver;
+use crate::triangulate::polygon_triangles;
+
+/// Resolves every material library to the same material data.
+pub(crate) struct MaterialData<'a>(pub(crate) &'a str);
+
+impl ResourceResolver for MaterialData<'_> {
+    fn read(&self, _path: &str) -> io::Result<String> {
+        Ok(self.0.to_string())
+    }
+}
 
 /// Holds the model that is being parsed from the respective file.
 pub struct Parser {
     pub model: Rc<RefCell<Model>>,
+    /// Name of the file being parsed, used to report errors.
+    pub file_name: String,
+    /// Options of the parsing. The default material is applied when the first line is read.
+    pub options: LoadOptions,
+    /// Line that is being parsed.
+    line: usize,
 }
 
 impl Parser {
     /// Initialize parser with the data fetched from js.
-    pub fn parse(scene_data: &str, mat_data:&str) -> Result<Parser, JsValue> {
-        let mut parser = Parser {
-            model: Rc::new(RefCell::new(Model::new())),
-        };
-        parser.read_data(scene_data, mat_data)?;
+    pub fn parse(scene_data: &str, mat_data: &str) -> Result<Parser, ObjError> {
+        Parser::parse_named("", scene_data, mat_data)
+    }
+
+    /// Initialize parser with the data and the name of the file it was read from.
+    /// Every material library is read from the same material data.
+    pub fn parse_named(file_name: &str, scene_data: &str, mat_data: &str)
+                       -> Result<Parser, ObjError> {
+        Parser::parse_with(file_name, scene_data, &MaterialData(mat_data))
+    }
+
+    /// Initialize parser with the data of the file at `file_name`.
+    /// The material libraries are loaded through the resolver, relative to the file.
+    pub fn parse_with(file_name: &str, scene_data: &str, resolver: &dyn ResourceResolver)
+                      -> Result<Parser, ObjError> {
+        let mut parser = Parser::new(file_name);
+        parser.read(scene_data, resolver)?;
         Ok(parser)
     }
 
-    /// Read the data in into the model.
-    fn read_data(&mut self, scene_data: &str, mat_data:&str) -> Result<(), JsValue> {
+    /// Create a parser for the file at `file_name`, to be fed line by line.
+    pub fn new(file_name: &str) -> Parser {
+        Parser::with_options(file_name, LoadOptions::new())
+    }
+
+    /// Create a parser for the file at `file_name` with the given options.
+    pub fn with_options(file_name: &str, options: LoadOptions) -> Parser {
+        Parser {
+            model: Rc::new(RefCell::new(Model::new())),
+            file_name: file_name.to_string(),
+            options,
+            line: 0,
+        }
+    }
+
+    /// Use the `material` for the faces without a material, or with one missing from the library.
+    pub fn set_default_material(&mut self, material: Material) {
         let mut model = self.model.borrow_mut();
+        let default = Rc::new(RefCell::new(material));
+        if Rc::ptr_eq(&model.matlib.0, &model.default_material) {
+            model.matlib.0 = Rc::clone(&default);
+        }
+        model.default_material = default;
+    }
+
+    /// Read the data in into the model.
+    pub fn read(&mut self, scene_data: &str, resolver: &dyn ResourceResolver)
+                -> Result<(), ObjError> {
         for line in scene_data.lines() {
-            let mut words = line[..].split_whitespace();
-            match words.next() {
-                Some("#") | None => continue,
-                Some("mtllib") => {
-                    let name: String = words.map(|x| " ".to_owned() + x).collect();
-                    MtlReader::load(&mut model.matlib, mat_data);
-                },
-                Some("v") => {
-                    if words.clone().count() <= 4 {
-                        self.parse_floats(words, &mut model.gv)?;
-                    } else if words.clone().count() == 6 {
-                        log!("This is not implemented")
-                        // self.parse_with_colors(words, &mut model.gv, &mut model.colors)?;
-                    }
-                },
-                Some("vt") => {
-                    self.parse_floats(words, &mut model.vt)?;
-                },
-                Some("vn") => {
-                    self.parse_floats(words, &mut model.vn)?;
-                },
-                Some("f") => {
-                    self.parse_face(words, &mut model);
-                },
-                Some("g") => {
-                    self.parse_group(&mut words, &mut model)?;
-                },
-                Some("o") => {
-                    self.parse_obj(words, &mut model)?;
-                },
-                Some("usemtl") => {
-                    self.use_material(words, &mut model)?;
-                }
+            self.read_line(line, resolver)?;
+        }
+        Ok(())
+    }
 
-                Some(_) => {}
+    /// Read the next line of the file in into the model.
+    /// The material libraries are loaded through the resolver, relative to the file.
+    /// Unless the options are strict, a malformed line is skipped with a warning.
+    pub fn read_line(&mut self, line: &str, resolver: &dyn ResourceResolver)
+                     -> Result<(), ObjError> {
+        if self.line == 0 {
+            if let Some(material) = self.options.default_material.clone() {
+                self.set_default_material(material);
             }
         }
-        Ok(())
+        self.line += 1;
+        match self.read_statement(line, resolver) {
+            Err(error) if !self.options.strict => {
+                self.model.borrow_mut().warnings.push(error);
+                Ok(())
+            },
+            result => result,
+        }
     }
 
-    /// Parse array of floats.
-    /// Usually used to parse vertex, colors, tex coords, and normals.
-    fn parse_floats(&self, words: SplitWhitespace, vals: &mut Vec<f32>)
-                        -> Result<(), JsValue> {
-        let count = words.clone().count();
-        match count {
-            3 => {
-                for p in words {
-                    match FromStr::from_str(p) {
-                        Ok(x) => vals.push(x),
-                        Err(e) => return Err(JsValue::from_str("Fetch error")),
-                    }
+    /// Read the statement on the line in into the model.
+    fn read_statement(&self, line: &str, resolver: &dyn ResourceResolver)
+                      -> Result<(), ObjError> {
+        let model = Rc::clone(&self.model);
+        let mut model = model.borrow_mut();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("#") | None => return Ok(()),
+            Some("mtllib") => {
+                for name in words {
+                    let path = resolver.resolve(&self.file_name, name);
+                    let data = resolver.read(&path).map_err(|e| ObjError::MissingResource {
+                        location: self.location(line, name),
+                        path: path.clone(),
+                        reason: e.to_string(),
+                    })?;
+                    let model = &mut *model;
+                    MtlReader::load(&mut model.matlib.1, &path, &data, resolver, self.options.strict,
+                                    &mut model.warnings)?;
                 }
             },
-
-            4 => {
-                let mut temp: Vec<f32> = Vec::new();
-                for p in words {
-                    match FromStr::from_str(p) {
-                        Ok(x) => temp.push(x),
-                        Err(e) => return Err(JsValue::from_str("Fetch error")),
-                    }
+            Some("v") => {
+                let values = self.parse_floats(line, words)?;
+                match values.len() {
+                    // The weight is only used by rational curves, so it doesn't move the vertex.
+                    3 | 4 => {
+                        model.gv.extend_from_slice(&values[..3]);
+                        if !model.colors.is_empty() {
+                            model.colors.extend_from_slice(&[1.0; 4]);
+                        }
+                    },
+                    6 | 7 => self.parse_with_colors(&values, &mut model),
+                    _ => return Err(self.malformed(line, line.trim())),
                 }
-                let w = temp[3];
-                vals.push(temp[0] / &w);
-                vals.push(temp[1] / &w);
-                vals.push(temp[2] / &w);
             },
-
-            6 => {
-                log!("Not implemented")
+            Some("vt") => {
+                let values = self.parse_floats(line, words)?;
+                match values.len() {
+                    1 => model.vt.extend_from_slice(&[values[0], 0.0]),
+                    2 | 3 => model.vt.extend_from_slice(&values[..2]),
+                    _ => return Err(self.malformed(line, line.trim())),
+                }
             },
-
-            2 => {
-                for p in words {
-                    match FromStr::from_str(p) {
-                        Ok(x) => vals.push(x),
-                        Err(e) => return Err(JsValue::from_str("Fetch error")),
-                    }
+            Some("vn") => {
+                let values = self.parse_floats(line, words)?;
+                match values.len() {
+                    3 => model.vn.extend_from_slice(&values),
+                    _ => return Err(self.malformed(line, line.trim())),
                 }
             },
+            Some("f") => {
+                self.parse_face(line, words, FaceType::Triangle, &mut model)?;
+            },
+            Some("l") if self.options.ignore_lines => {},
+            Some("p") if self.options.ignore_points => {},
+            Some("l") => {
+                self.parse_face(line, words, FaceType::Line, &mut model)?;
+            },
+            Some("p") => {
+                self.parse_face(line, words, FaceType::Point, &mut model)?;
+            },
+            Some("g") => {
+                self.parse_group(words, &mut model)?;
+            },
+            Some("mg") => {
+                self.parse_merging_group(line, words, &mut model)?;
+            },
+            Some("o") => {
+                self.parse_obj(line, words, &mut model)?;
+            },
+            Some("usemtl") => {
+                self.use_material(line, words, &mut model)?;
+            }
+            Some("s") => {
+                model.cur_smoothing_group = match words.next() {
+                    Some("off") => 0,
+                    Some(x) => u32::from_str(x).map_err(|_| self.malformed(line, x))?,
+                    None => return Err(self.malformed(line, line.trim())),
+                };
+            }
 
-            _ => {}
+            Some(_) => {}
         }
         Ok(())
     }
 
-    fn parse_with_colors(&self,
-                             words: SplitWhitespace,
-                             data: &mut Vec<f32>,
-                             color: &mut Vec<f32>,
-    ) -> Result<(), JsValue> {
-        // Not implemented yet.
-        Ok(())
+    /// Locate the token within the line that is being parsed.
+    fn location(&self, line: &str, token: &str) -> Location {
+        Location::new(&self.file_name, self.line, line, token)
     }
 
-    /// Parse face information from the object file.
-    fn parse_face(&self, words: SplitWhitespace, model: &mut Model) -> Result<(), JsValue> {
-        // Make it work for points and lines too!
-        let mut face: Face = Face::new();
-        face.face_type = FaceType::Triangle;
-        for each in words {
-            for (idx, data) in each.split("/").enumerate() {
-                if !data.is_empty() {
-                    match isize::from_str(data) {
-                        Ok(x) => if x < 0 as isize{
-                            match idx {
-                                0 => face.vertices.push((x  + (model.gv.len() / 3) as isize) as usize),
-                                1 => face.textures.push((x + (model.vt.len() / 2) as isize) as usize),
-                                2 => face.normals.push((x  + (model.vn.len() / 3) as isize) as usize),
-                                _ => panic!("Error while parsing face")
-                            }
-                        } else {
-                            match idx {
-                                0 => face.vertices.push(x as usize - 1),
-                                1 => face.textures.push(x as usize - 1),
-                                2 => face.normals.push(x as usize - 1),
-                                _ => panic!("Error while parsing face")
-                            }
-                        },
-                        Err(_) => return Err(JsValue::from_str("No face for you!"))
-                    }
-                }
-           }
+    /// Error for statements that are missing components or have too many of them.
+    /// The `token` has to be a slice of the `line` to report the column.
+    fn malformed(&self, line: &str, token: &str) -> ObjError {
+        ObjError::MalformedStatement {
+            location: self.location(line, token),
+            token: token.to_string(),
         }
+    }
 
-        // Triangulate the parsed face.
-        if face.vertices.len() > 3 {
-            let mut temp = Vec::new();
-            let a = face.vertices[0];
-            let mut b = face.vertices[1];
-            for c in face.vertices.iter().skip(2) {
-                temp.push(a);
-                temp.push(b);
-                temp.push(*c);
-                b = *c;
+    /// Parse array of floats.
+    /// Usually used to parse vertex, colors, tex coords, and normals.
+    fn parse_floats(&self, line: &str, words: SplitWhitespace) -> Result<Vec<f32>, ObjError> {
+        let mut vals = Vec::new();
+        for p in words {
+            match FromStr::from_str(p) {
+                Ok(x) => vals.push(x),
+                Err(_) => return Err(ObjError::BadFloat {
+                    location: self.location(line, p),
+                    token: p.to_string(),
+                }),
             }
-            face.vertices = temp;
         }
-        if face.normals.len() > 3 {
-            let mut temp = Vec::new();
-            let a = face.normals[0];
-            let mut b = face.normals[1];
-            for c in face.normals.iter().skip(2) {
-                temp.push(a);
-                temp.push(b);
-                temp.push(*c);
-                b = *c;
+        Ok(vals)
+    }
+
+    /// Split the position and the trailing RGB or RGBA color of the vertex.
+    /// The vertices read before the first color are given white.
+    fn parse_with_colors(&self, values: &[f32], model: &mut Model) {
+        if model.colors.is_empty() {
+            model.colors = vec![1.0; model.gv.len() / 3 * 4];
+        }
+        model.gv.extend_from_slice(&values[..3]);
+        model.colors.extend_from_slice(&values[3..]);
+        if values.len() == 6 {
+            model.colors.push(1.0);
+        }
+    }
+
+    /// Parse a single index of the face and resolve it against the `count` of read elements.
+    fn parse_index(&self, line: &str, token: &str, data: &str, count: usize)
+                   -> Result<usize, ObjError> {
+        let x = match isize::from_str(data) {
+            Ok(x) if x != 0 => x,
+            _ => return Err(ObjError::BadIndex {
+                location: self.location(line, data),
+                token: token.to_string(),
+            }),
+        };
+        let index = if x < 0 { x + count as isize } else { x - 1 };
+        if index < 0 || index as usize >= count {
+            return Err(ObjError::OutOfRange {
+                location: self.location(line, data),
+                token: token.to_string(),
+                count,
+            });
+        }
+        Ok(index as usize)
+    }
+
+    /// Parse face, line or point information from the object file.
+    /// Points only refer to vertices, lines may also refer to textures.
+    fn parse_face(&self, line: &str, words: SplitWhitespace, face_type: FaceType,
+                  model: &mut Model) -> Result<(), ObjError> {
+        let (components, min_corners) = match face_type {
+            FaceType::Point => (1, 1),
+            FaceType::Line => (2, 2),
+            _ => (3, 3),
+        };
+        let mut face: Face = Face::new();
+        face.face_type = face_type;
+        for each in words {
+            if each.split('/').count() > components || each.starts_with('/') {
+                return Err(self.malformed(line, each));
             }
-            face.normals = temp;
+            for (idx, data) in each.split('/').enumerate() {
+                if data.is_empty() {
+                    continue;
+                }
+                match idx {
+                    0 => face.vertices.push(self.parse_index(line, each, data, model.gv.len() / 3)?),
+                    1 => face.textures.push(self.parse_index(line, each, data, model.vt.len() / 2)?),
+                    _ => face.normals.push(self.parse_index(line, each, data, model.vn.len() / 3)?),
+                }
+            }
+        }
+        let corners = face.vertices.len();
+        if corners < min_corners
+            || (!face.textures.is_empty() && face.textures.len() != corners)
+            || (!face.normals.is_empty() && face.normals.len() != corners) {
+            return Err(self.malformed(line, line.trim()));
         }
-        if face.textures.len() > 3 {
-            let mut temp = Vec::new();
-            let a = face.textures[0];
-            let mut b = face.textures[1];
-            for c in face.textures.iter().skip(2) {
-                temp.push(a);
-                temp.push(b);
-                temp.push(*c);
-                b = *c;
+
+        // Triangulate the parsed face, or keep it as a polygon.
+        if face.face_type == FaceType::Triangle && face.vertices.len() > 3 {
+            if self.options.triangulate {
+                let triangles = polygon_triangles(model, &face.vertices);
+                face.vertices = triangle_corners(&face.vertices, &triangles);
+                face.textures = triangle_corners(&face.textures, &triangles);
+                face.normals = triangle_corners(&face.normals, &triangles);
+            } else {
+                face.face_type = FaceType::Polygon;
             }
-            face.textures = temp;
         }
 
+        self.select_mesh(model)?;
         face.material = Rc::clone(&model.matlib.0);
-        model.meshes[model.cur_mesh].faces.push(face);
+        face.smoothing_group = model.cur_smoothing_group;
+        face.groups = model.grplib.0.clone();
+        face.merging_group = model.cur_merging_group;
+        let has_colors = !model.colors.is_empty();
+        let mesh = &mut model.meshes[model.cur_mesh];
+        mesh.has_normals |= !face.normals.is_empty();
+        mesh.has_vertex_colors |= has_colors;
+        mesh.faces.push(face);
         Ok(())
     }
 
     /// Parse groups from the .obj file.
-    fn parse_group(&self, words:&mut SplitWhitespace, model: &mut Model) -> Result<(), JsValue>
-    {
-        match words.next() {
-            Some(x) => {
-                if &model.grplib.0 == x {
-                    return Ok(())
+    /// The next faces belong to every group named on the line, or to none without names.
+    fn parse_group(&self, words: SplitWhitespace, model: &mut Model) -> Result<(), ObjError> {
+        let mut active = Vec::new();
+        for name in words {
+            let index = match model.grplib.1.iter().position(|x| x == name) {
+                Some(index) => index,
+                None => {
+                    model.grplib.1.push(name.to_string());
+                    model.grplib.1.len() - 1
                 }
-                let group_face = match model.grplib.1.entry(x.to_string()) {
-                    Entry::Occupied(o) => model.grplib.1.get(&x.to_string()).unwrap(),
-                    Entry::Vacant(v) => v.insert(Vec::new()),
-                };
-                self.create_object(x, model);
-                model.grplib.0 = x.to_string();
-            },
-            None => return Err(JsValue::from_str("Can't parse groups."))
+            };
+            if !active.contains(&index) {
+                active.push(index);
+            }
+        }
+        model.grplib.0 = active;
+        Ok(())
+    }
 
+    /// Parse the merging group and its resolution from the .obj file.
+    fn parse_merging_group(&self, line: &str, mut words: SplitWhitespace, model: &mut Model)
+                           -> Result<(), ObjError> {
+        let group = match words.next() {
+            Some("off") => 0,
+            Some(x) => u32::from_str(x).map_err(|_| self.malformed(line, x))?,
+            None => return Err(self.malformed(line, line.trim())),
+        };
+        if let Some(x) = words.next() {
+            let resolution = f32::from_str(x).map_err(|_| ObjError::BadFloat {
+                location: self.location(line, x),
+                token: x.to_string(),
+            })?;
+            model.merging_resolutions.insert(group, resolution);
         }
+        model.cur_merging_group = group;
         Ok(())
     }
 
     /// Parse objects from the .obj file.
-    fn parse_obj(&self, words: SplitWhitespace, model: &mut Model) -> Result<(), JsValue>
+    fn parse_obj(&self, line: &str, words: SplitWhitespace, model: &mut Model) -> Result<(), ObjError>
     {
 
         let mut iter = model.objects.iter();
-        let name: String = words.collect();
+        let name = rest_of_line(line, words).to_string();
         let obj_index = iter.position(|x| x.name == name );
         match obj_index {
             Some(o) => model.cur_obj = o,
@@ -235,9 +369,9 @@ impl Parser {
     }
 
     /// A helper function to creating objects for parsing.
-    fn create_object(&self, name: &str, model: &mut Model) -> Result<(), JsValue> {
+    fn create_object(&self, name: &str, model: &mut Model) -> Result<(), ObjError> {
         let mut iter = model.objects.iter();
-        let obj_index = iter.position(|x| &x.name == name );
+        let obj_index = iter.position(|x| x.name == name );
         match obj_index {
             Some(x) => model.cur_obj = x,
             None => {
@@ -245,41 +379,78 @@ impl Parser {
                 obj.name = name.to_string();
                 model.cur_obj = model.objects.len();
                 model.objects.push(obj);
-                self.create_mesh(name, model);
             }
         }
-        model.meshes[model.cur_mesh].material = Rc::clone(&model.matlib.0);
+        Ok(())
+    }
 
+    /// Select the mesh of the current object using the active material for the next face.
+    /// The mesh, and the object if there is none, is created when it doesn't exist yet.
+    fn select_mesh(&self, model: &mut Model) -> Result<(), ObjError> {
+        if model.objects.is_empty() {
+            self.create_object("default", model)?;
+        }
+        let object = &model.objects[model.cur_obj];
+        let mesh = object.meshes.iter()
+            .find(|x| Rc::ptr_eq(&model.meshes[**x].material, &model.matlib.0));
+        match mesh {
+            Some(x) => model.cur_mesh = *x,
+            None => {
+                let name = object.name.clone();
+                self.create_mesh(&name, model)?;
+            }
+        }
         Ok(())
     }
 
     /// A helper function to create mesh for parsing.
-    fn create_mesh(&self, name: &str, model: &mut Model) -> Result<(), JsValue> {
+    fn create_mesh(&self, name: &str, model: &mut Model) -> Result<(), ObjError> {
         let mut mesh = Mesh::new();
         mesh.name = name.to_string();
+        mesh.material = Rc::clone(&model.matlib.0);
         model.meshes.push(mesh);
-        let index = model.meshes.len() - 1 as usize;
+        let index = model.meshes.len() - 1;
         model.cur_mesh = index;
         model.objects[model.cur_obj].meshes.push(index);
         Ok(())
     }
 
-    // TODO: Allow loading of default material when there is no information present about material
     /// A helper function to create material for parsing.
-    fn use_material(&self, word: SplitWhitespace, model: &mut Model) -> Result<(), JsValue> {
-        let name:String = word.collect();
-        let material = match model.matlib.1.entry(name.clone()) {
-            Entry::Occupied(o) => model.matlib.1.get(&name).unwrap(),
-            Entry::Vacant(v) => {
-                model.matlib.1.get("initialShadingGroup").unwrap()
-                // let message = String::from("The material doesn't exists ") + &name;
-                // return Err(JsValue::from_str(&message));
+    /// The default material is used when the material isn't in the library,
+    /// and a warning naming it is recorded.
+    fn use_material(&self, line: &str, word: SplitWhitespace, model: &mut Model)
+                    -> Result<(), ObjError> {
+        let token = rest_of_line(line, word);
+        let name = token.to_string();
+        let material = match model.matlib.1.get(&name) {
+            Some(material) => Rc::clone(material),
+            None => {
+                model.warnings.push(ObjError::UnknownMaterial {
+                    location: self.location(line, token),
+                    name,
+                });
+                Rc::clone(&model.default_material)
             }
         };
-        model.matlib.0 = material.clone();
+        model.matlib.0 = material;
         Ok(())
-
     }
 
 
 }
+
+/// The rest of the line from the next of its `words`, such as a name that may contain spaces.
+pub(crate) fn rest_of_line<'a>(line: &'a str, mut words: SplitWhitespace) -> &'a str {
+    match words.next() {
+        Some(first) => line[first.as_ptr() as usize - line.as_ptr() as usize..].trim_end(),
+        None => "",
+    }
+}
+
+/// Indices of the corners of the triangles, or nothing when the face has no such indices.
+fn triangle_corners(indices: &[usize], triangles: &[[usize; 3]]) -> Vec<usize> {
+    if indices.is_empty() {
+        return Vec::new();
+    }
+    triangles.iter().flatten().map(|x| indices[*x]).collect()
+}
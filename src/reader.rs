@@ -1,169 +1,302 @@
-use wasm_bindgen::{prelude::*, JsCast};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{console, window, Response};
-
+use std::collections::HashMap;
 use std::rc::Rc;
-use std::cell::{RefCell, Ref};
-use crate::log;
+use std::ops::Range;
 use crate::data::*;
+use crate::error::{Location, ObjError};
+use crate::normals::*;
+use crate::options::LoadOptions;
+use crate::resolver::ResourceResolver;
+use crate::triangulate::face_triangles;
 pub use crate::parser::*;
 pub use crate::scene::*;
+#[cfg(feature = "web_sys")]
+pub use crate::loader::DataLoader;
 
 
-/// Allows external js to load the information object file.
-#[wasm_bindgen]
-pub struct DataLoader {
-    reader: ObjReader,
-    mat_data: String,
-    scene_data: String,
-}
-
-#[wasm_bindgen]
-impl DataLoader {
-    pub fn new() -> Result<DataLoader, JsValue> {
-        let loader =  DataLoader {
-            reader: ObjReader::new()?,
-            mat_data: String::new(),
-            scene_data: String::new(),
-        };
-        Ok(loader)
-    }
-    pub fn load_scene(&mut self, data: String) {
-        self.scene_data = data;
-    }
-
-    pub fn load_material(&mut self, data: String) {
-        self.mat_data = data;
-    }
-
-    pub fn load(&mut self) -> Result<(), JsValue> {
-        self.reader.read(&self.scene_data, &self.mat_data)?;
-        Ok(())
-    }
+/// Where the normal of a face corner comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum NormalRef {
+    /// Index of the normal read from the file.
+    Read(usize),
+    /// Normal generated for the position within the smoothing group.
+    Smooth(u32),
+    /// Normal of the triangle at the given corner of the face, which isn't smoothed.
+    /// Polygons have a single normal, at triangle 0.
+    Flat(usize, usize),
+    /// The corner has no normal.
+    Missing,
 }
 
+/// Position, texture and normal of a face corner.
+type Corner = (usize, Option<usize>, NormalRef);
 
 /// Loads the scene from the given model
 pub struct ObjReader {
     pub scene: SceneGraph,
+    /// Options of the parsing and of the filled scene.
+    pub options: LoadOptions,
+    /// Problems that didn't stop the reading, such as unknown materials.
+    pub warnings: Vec<ObjError>,
 }
 
 
 impl ObjReader{
 
     /// Initializes new graph.
-    pub fn new () -> Result<ObjReader, JsValue> {
-        let mut result = ObjReader {
-            scene: SceneGraph::new()
+    pub fn new () -> Result<ObjReader, ObjError> {
+        let result = ObjReader {
+            scene: SceneGraph::new(),
+            options: LoadOptions::new(),
+            warnings: Vec::new(),
         };
         Ok(result)
     }
 
+    /// Initializes new graph, read with the given options.
+    pub fn with_options(options: LoadOptions) -> Result<ObjReader, ObjError> {
+        let mut reader = ObjReader::new()?;
+        reader.options = options;
+        Ok(reader)
+    }
+
     /// Loads the data and parses it into the model.
-    pub fn read(&mut self, scene_data: &str, mat_data: &str) -> Result<(), JsValue> {
-        let parser = Parser::parse(scene_data, mat_data)?;
+    pub fn read(&mut self, scene_data: &str, mat_data: &str) -> Result<(), ObjError> {
+        self.read_named("", scene_data, mat_data)
+    }
+
+    /// Loads the data and parses it into the model, naming the file in the reported errors.
+    pub fn read_named(&mut self, file_name: &str, scene_data: &str, mat_data: &str)
+                      -> Result<(), ObjError> {
+        self.parse(file_name, scene_data, &MaterialData(mat_data))
+    }
+
+    /// Loads the file at `file_name` and its material libraries through the resolver.
+    pub fn read_with(&mut self, file_name: &str, resolver: &dyn ResourceResolver)
+                     -> Result<(), ObjError> {
+        let scene_data = resolver.read(file_name).map_err(|e| ObjError::MissingResource {
+            location: Location::whole_file(file_name),
+            path: file_name.to_string(),
+            reason: e.to_string(),
+        })?;
+        self.parse(file_name, &scene_data, resolver)
+    }
+
+    /// Loads the model that has already been parsed, such as by the streaming parser.
+    pub fn read_model(&mut self, model: &Model) -> Result<(), ObjError> {
+        self.fill_nodes(model)
+    }
+
+    /// Parses the data into the model and fills the graph with it.
+    fn parse(&mut self, file_name: &str, scene_data: &str, resolver: &dyn ResourceResolver)
+             -> Result<(), ObjError> {
+        let mut parser = Parser::with_options(file_name, self.options.clone());
+        parser.read(scene_data, resolver)?;
         let model = parser.model.borrow();
-        self.fill_nodes(&model)?;
-        Ok(())
+        self.fill_nodes(&model)
     }
 
     /// Adds the information to each scene node in the graph.
-    fn fill_nodes(&mut self, model: &Ref<Model>) -> Result<(), JsValue>
+    fn fill_nodes(&mut self, model: &Model) -> Result<(), ObjError>
     {
-        let mut root = SceneNode::new();
-        self.scene.nodes.push(root);
-        let root_index = self.scene.nodes.len() - 1;
-        let mut children: Vec<usize> = Vec::new();
-        let mut index: usize = 0;
-        self.load_materials(&model);
-        for obj in model.objects.iter() {
-            let mut node = SceneNode::new();
-            node.name = obj.name.clone();
-            node.parent = Some(Index { index: root_index});
-            self.load_meshes(&model, &obj, &mut node, &root_index);
-            self.scene.nodes.push(node);
-            if obj.sub_objects.len() > 0 {
-                index += 1 as usize;
-                children.push(index.clone())
+        self.warnings.extend(model.warnings.iter().cloned());
+        let root_index = self.scene.add_node("", None);
+        self.load_materials(model);
+        for name in model.grplib.1.iter() {
+            if !self.scene.groups.contains(name) {
+                self.scene.groups.push(name.clone());
             }
         }
-        if children.len() > 0 {
-            for i in children.iter() {
-                let obj = &model.objects[i.clone()];
-                for child in obj.sub_objects.iter() {
-                    self.scene.nodes[child.clone()].parent = Some(Index {index: (i + 1 as usize)})
+        let missing_normals = model.meshes.iter()
+            .any(|mesh| mesh.faces.iter().any(needs_normals));
+        let normals = if self.options.generate_normals && missing_normals {
+            Some(SmoothNormals::new(model, self.options.normal_weighting))
+        } else {
+            None
+        };
+        // Nodes keyed by their parent and name, so the objects and groups sharing
+        // the levels of their names share the nodes.
+        let mut paths: HashMap<(usize, String), usize> = HashMap::new();
+        for obj in model.objects.iter() {
+            let node = self.node_path(root_index, &obj.name, &mut paths);
+            self.scene.nodes[node].transformation = obj.transform;
+            self.load_meshes(model, obj, node, normals.as_ref());
+            let mut groups: Vec<usize> = Vec::new();
+            let faces = obj.meshes.iter().flat_map(|x| model.meshes[*x].faces.iter());
+            for group in faces.flat_map(|x| x.groups.iter()) {
+                if !groups.contains(group) {
+                    groups.push(*group);
                 }
             }
+            for group in groups {
+                let name = &model.grplib.1[group];
+                let group_node = self.node_path(node, name, &mut paths);
+                self.scene.nodes[group_node].group = Some(name.clone());
+            }
         }
         Ok(())
     }
 
-    /// Load materials into the graph.
-    fn load_materials(&mut self, model:&Ref<Model>) {
-        for (material_name, material) in &model.matlib.1 {
-            self.scene.materials.push(material.borrow().clone());
+    /// Node at the `/` separated path below the parent, creating the missing levels.
+    fn node_path(&mut self, parent: usize, path: &str,
+                 paths: &mut HashMap<(usize, String), usize>) -> usize {
+        let mut levels: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+        if levels.is_empty() {
+            levels.push(path);
         }
+        let mut node = parent;
+        for level in levels {
+            node = match paths.get(&(node, level.to_string())) {
+                Some(child) => *child,
+                None => {
+                    let child = self.scene.add_node(level, Some(node));
+                    paths.insert((node, level.to_string()), child);
+                    child
+                }
+            };
+        }
+        node
+    }
 
+    /// Load materials into the graph, sorted by name so the scene is the same on every run.
+    /// The default material is included when any of the meshes uses it.
+    fn load_materials(&mut self, model:&Model) {
+        let mut materials: Vec<Material> = model.matlib.1.values().map(|x| x.borrow().clone()).collect();
+        materials.sort_by(|a, b| a.name.cmp(&b.name));
+        self.scene.materials.extend(materials);
+        if model.meshes.iter().any(|x| Rc::ptr_eq(&x.material, &model.default_material)) {
+            self.scene.materials.push(model.default_material.borrow().clone());
+        }
     }
 
     /// Load meshes into the graph.
-    fn load_meshes(&mut self, model: &Ref<Model>, obj:&Object, node: &mut SceneNode, root:&usize) {
+    fn load_meshes(&mut self, model: &Model, obj:&Object, node: usize,
+                   normals: Option<&SmoothNormals>) {
         for mesh in obj.meshes.iter() {
-            let model_mesh =  &model.meshes[mesh.clone()];
-            let filled_mesh = self.filled_mesh(&model, &model_mesh);
+            let model_mesh =  &model.meshes[*mesh];
+            let mut filled_mesh = self.filled_mesh(model, model_mesh, normals);
+            if self.options.generate_tangents {
+                filled_mesh.generate_tangents();
+            }
             let mesh_index = self.scene.meshes.len();
-            node.meshes.push(mesh_index.clone());
+            self.scene.nodes[node].meshes.push(mesh_index);
             self.scene.meshes.push(filled_mesh);
         }
 
     }
 
     /// Fill each mesh with the mesh information.
-    fn filled_mesh(&self, model: &Ref<Model>, mesh:&Mesh) -> SceneMesh {
+    /// In indexed mode, the corners sharing the same position, texture and normal
+    /// are merged into a single vertex.
+    fn filled_mesh(&self, model: &Model, mesh:&Mesh, normals: Option<&SmoothNormals>)
+                   -> SceneMesh {
         let mut scene_mesh = SceneMesh::new();
         scene_mesh.name = mesh.name.clone();
-        let mut new_index:usize = 0;
-        for face in &mesh.faces {
+        let has_textures = mesh.faces.iter().any(|face| !face.textures.is_empty());
+        let has_normals = mesh.faces.iter()
+            .any(|face| !face.normals.is_empty() || (normals.is_some() && needs_normals(face)));
+        let mut shared: HashMap<Corner, usize> = HashMap::new();
+        let mut lines: Vec<usize> = Vec::new();
+        let mut points: Vec<usize> = Vec::new();
+        let mut strips: Vec<Range<usize>> = Vec::new();
+        for (face_index, face) in mesh.faces.iter().enumerate() {
             // Use the face to fetch vertices.
             let mut new_face = SceneFace::new();
-            let len: u32 = face.vertices.len() as u32;
-            // let a = new_index;
-            // let mut b = new_index + 1;
-            for idx in 0..len {
-                let index = idx as usize;
-                scene_mesh.gv.push(model.gv[face.vertices[index] * 3]);
-                scene_mesh.gv.push(model.gv[face.vertices[index] * 3 + 1 as usize]);
-                scene_mesh.gv.push(model.gv[face.vertices[index] * 3 + 2 as usize]);
-                // TODO: If vt empty
-                scene_mesh.vt.push(model.vt[face.textures[index] * 2]);
-                scene_mesh.vt.push(model.vt[face.textures[index] * 2+ 1 as usize]);
-                // TODO: If vn empty
-                scene_mesh.vn.push(model.vn[face.normals[index] * 3 ]);
-                scene_mesh.vn.push(model.vn[face.normals[index] * 3 + 1 as usize]);
-                scene_mesh.vn.push(model.vn[face.normals[index] * 3 + 2 as usize]);
-                scene_mesh.face_indices.push(new_index);
+            new_face.face_type = face.face_type;
+            new_face.smoothing_group = face.smoothing_group;
+            new_face.merging_group = face.merging_group;
+            for group in face.groups.iter() {
+                scene_mesh.groups.entry(model.grplib.1[*group].clone()).or_default()
+                    .push(face_index);
+            }
+            let generated = normals.is_some() && needs_normals(face);
+            let polygon = face.face_type == FaceType::Polygon;
+            for idx in 0..face.vertices.len() {
+                let normal = match face.normals.get(idx) {
+                    Some(vn) => NormalRef::Read(*vn),
+                    None if !generated => NormalRef::Missing,
+                    None if face.smoothing_group == 0 && polygon => NormalRef::Flat(face_index, 0),
+                    None if face.smoothing_group == 0 => NormalRef::Flat(face_index, idx / 3),
+                    None => NormalRef::Smooth(face.smoothing_group),
+                };
+                let corner = (face.vertices[idx], face.textures.get(idx).cloned(), normal);
+                let new_index = match shared.get(&corner) {
+                    Some(index) => *index,
+                    None => {
+                        let index = scene_mesh.gv.len() / 3;
+                        let normal = match normal {
+                            NormalRef::Read(vn) => {
+                                [model.vn[vn * 3], model.vn[vn * 3 + 1], model.vn[vn * 3 + 2]]
+                            },
+                            NormalRef::Smooth(group) => normals.unwrap().get(corner.0, group),
+                            NormalRef::Flat(_, _) if polygon => flat_normal(model, &face.vertices),
+                            NormalRef::Flat(_, triangle) => {
+                                flat_normal(model, &face.vertices[triangle * 3..triangle * 3 + 3])
+                            },
+                            NormalRef::Missing => [0.0; 3],
+                        };
+                        self.push_vertex(model, corner, normal, has_textures, has_normals,
+                                         &mut scene_mesh);
+                        if mesh.has_vertex_colors {
+                            let v = corner.0;
+                            scene_mesh.colors.extend_from_slice(&model.colors[v * 4..v * 4 + 4]);
+                        }
+                        if self.options.indexed {
+                            shared.insert(corner, index);
+                        }
+                        index
+                    }
+                };
                 new_face.indices.push(new_index);
-                // if idx >= 2 {
-                //     scene_mesh.face_indices.push(a);
-                //     scene_mesh.face_indices.push(b);
-                //     new_face.indices.push(a);
-                //     new_face.indices.push(b);
-                //     scene_mesh.face_indices.push(new_index);
-                //     new_face.indices.push(new_index);
-                //     b = new_index;
-                // }
-                new_index += (1 as usize);
             }
+            let start = scene_mesh.face_indices.len();
+            match face.face_type {
+                FaceType::Point => points.extend_from_slice(&new_face.indices),
+                FaceType::Line => {
+                    strips.push(lines.len()..lines.len() + new_face.indices.len());
+                    lines.extend_from_slice(&new_face.indices);
+                },
+                FaceType::Polygon => {
+                    let triangles = face_triangles(model, face);
+                    let indices = triangles.iter().flatten().map(|x| new_face.indices[*x]);
+                    scene_mesh.face_indices.extend(indices);
+                },
+                FaceType::Triangle => scene_mesh.face_indices.extend_from_slice(&new_face.indices),
+            }
+            new_face.triangles = start..scene_mesh.face_indices.len();
             scene_mesh.faces.push(new_face);
         }
+        let start = scene_mesh.face_indices.len();
+        scene_mesh.triangles = 0..start;
+        scene_mesh.line_strips = strips.into_iter()
+            .map(|strip| strip.start + start..strip.end + start)
+            .collect();
+        scene_mesh.face_indices.append(&mut lines);
+        let start = scene_mesh.face_indices.len();
+        scene_mesh.face_indices.append(&mut points);
+        scene_mesh.points = start..scene_mesh.face_indices.len();
         let material = &mesh.material.borrow();
         let mut mat_iter = self.scene.materials.iter();
         let index = mat_iter.position(|x| x.name == material.name);
-        match index {
-            Some(x) => scene_mesh.material = Some(Index {index: x}),
-            None => ()
+        if let Some(x) = index {
+            scene_mesh.material = Some(Index {index: x});
         }
         scene_mesh
     }
-    // TODO: Support Indexing and loading of indexed data. Currently every vertices are loaded.
+
+    /// Copy the position, texture and normal of the corner into the mesh.
+    /// Corners without texture or normal get zeroes when the rest of the mesh has them.
+    fn push_vertex(&self, model: &Model, corner: Corner, normal: [f32; 3], has_textures: bool,
+                   has_normals: bool, scene_mesh: &mut SceneMesh) {
+        let (v, vt, _) = corner;
+        scene_mesh.gv.extend_from_slice(&model.gv[v * 3..v * 3 + 3]);
+        if has_textures {
+            match vt {
+                Some(vt) => scene_mesh.vt.extend_from_slice(&model.vt[vt * 2..vt * 2 + 2]),
+                None => scene_mesh.vt.extend_from_slice(&[0.0; 2]),
+            }
+        }
+        if has_normals {
+            scene_mesh.vn.extend_from_slice(&normal);
+        }
+    }
 }
@@ -0,0 +1,92 @@
+//! Resolves the .mtl libraries and the textures referenced by the parsed files.
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Provides the content of the files referenced by the .obj and .mtl files.
+pub trait ResourceResolver {
+    /// Read the content of the file at the resolved `path`.
+    fn read(&self, path: &str) -> io::Result<String>;
+
+    /// Resolve the `path` referenced from within the file at `base`.
+    /// Relative paths are joined onto the directory of `base`.
+    fn resolve(&self, base: &str, path: &str) -> String {
+        join_path(base, path)
+    }
+}
+
+/// Join the relative `path` onto the directory of the file at `base`.
+/// Absolute paths and urls are returned as they are.
+pub fn join_path(base: &str, path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/')
+        || path.contains("://")
+        || path.as_bytes().get(1) == Some(&b':');
+    if absolute {
+        return path;
+    }
+    let base = base.replace('\\', "/");
+    let mut parts: Vec<&str> = match base.rfind('/') {
+        Some(end) => base[..end].split('/').collect(),
+        None => Vec::new(),
+    };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {},
+            ".." if !parts.is_empty() && parts[parts.len() - 1] != ".." => {
+                parts.pop();
+            },
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Resolves the files from the data held in memory, keyed by their path.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    pub files: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    /// Create an empty resolver.
+    pub fn new() -> Self {
+        MemoryResolver {
+            files: HashMap::new(),
+        }
+    }
+
+    /// Add the content of the file at `path`.
+    pub fn insert(&mut self, path: &str, data: &str) {
+        self.files.insert(join_path("", path), data.to_string());
+    }
+}
+
+impl ResourceResolver for MemoryResolver {
+    fn read(&self, path: &str) -> io::Result<String> {
+        match self.files.get(path) {
+            Some(data) => Ok(data.clone()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "no such file in memory")),
+        }
+    }
+}
+
+/// Resolves the files from the file system, relative to the `root` directory.
+#[derive(Clone, Debug, Default)]
+pub struct FileResolver {
+    pub root: PathBuf,
+}
+
+impl FileResolver {
+    /// Create a resolver reading the files within `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FileResolver { root: root.into() }
+    }
+}
+
+impl ResourceResolver for FileResolver {
+    fn read(&self, path: &str) -> io::Result<String> {
+        fs::read_to_string(self.root.join(path))
+    }
+}
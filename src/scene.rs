@@ -1,32 +1,58 @@
 use crate::data::*;
+use crate::bounds::{transform_point, Bounds};
+use crate::gltf;
+use crate::normalize::{self, FitVolume, Normalization};
+use crate::tangents;
+use crate::traversal::{BreadthFirst, DepthFirst};
+use std::collections::BTreeMap;
 use std::rc::Rc;
+use std::ops::Range;
 use cgmath::{Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
 
 /// Hold index.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Index {
     pub index: usize,
 }
 
 /// Defines the face and becomes an interface to access the face information.
 /// It holds the indices to access the faces present within the mesh.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SceneFace { // Is questionable? Do I need it?
+    /// Primitive drawn by the face.
+    pub face_type: FaceType,
     /// List of indices pointing to the vertices, normals and textures.
     pub indices: Vec<usize>,
+    /// Range of the mesh's `face_indices` holding the triangles of the face.
+    pub triangles: Range<usize>,
+    /// Smoothing group of the face, 0 if the face is flat shaded.
+    pub smoothing_group: u32,
+    /// Merging group of the face, 0 if the face isn't merged.
+    pub merging_group: u32,
 }
 
 impl SceneFace {
     pub fn new() -> Self {
         SceneFace {
+            face_type: FaceType::Triangle,
             indices: Vec::new(),
+            triangles: 0..0,
+            smoothing_group: 0,
+            merging_group: 0,
         }
     }
 
 }
 
+impl Default for SceneFace {
+    fn default() -> Self {
+        SceneFace::new()
+    }
+}
+
 /// Store vertices, normals, textures, colors, and list of faces.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SceneMesh {
     /// Name of the
     pub name: String,
@@ -36,14 +62,24 @@ pub struct SceneMesh {
     pub vn: Vec<f32>,
     /// List of textures
     pub vt: Vec<f32>,
-    /// List of colors
+    /// List of colors, as RGBA for each vertex
     pub colors: Vec<f32>,
+    /// List of tangents, as xyz and the handedness w for each vertex
+    pub tangents: Vec<f32>,
     /// Material assigned to this mesh.
     pub material: Option<Index>,
     /// list of faces.
     pub faces: Vec<SceneFace>,
-    /// All face indices.
+    /// All face indices, ordered as triangles, line strips and then points.
     pub face_indices: Vec<usize>,
+    /// Range of `face_indices` to draw as triangles.
+    pub triangles: Range<usize>,
+    /// Ranges of `face_indices` to draw as line strips, one for each line.
+    pub line_strips: Vec<Range<usize>>,
+    /// Range of `face_indices` to draw as points.
+    pub points: Range<usize>,
+    /// Faces of each group, as indices into `faces`, keyed by the name of the group.
+    pub groups: BTreeMap<String, Vec<usize>>,
 }
 
 impl SceneMesh {
@@ -54,16 +90,55 @@ impl SceneMesh {
             vn: Vec::new(),
             vt: Vec::new(),
             colors: Vec::new(),
+            tangents: Vec::new(),
             material: None,
             faces: Vec::new(),
             face_indices: Vec::new(),
+            triangles: 0..0,
+            line_strips: Vec::new(),
+            points: 0..0,
+            groups: BTreeMap::new(),
         }
     }
 }
 
+impl SceneMesh {
+    /// Positions of the vertices.
+    pub fn positions(&self) -> Vec<Vector3<f32>> {
+        self.gv.chunks(3).map(|x| Vector3::new(x[0], x[1], x[2])).collect()
+    }
+
+    /// Bounding volumes of the vertices, in the space of the mesh.
+    pub fn bounds(&self) -> Bounds {
+        Bounds::from_points(&self.positions())
+    }
+
+    /// Indices of the triangles of the faces in the group, to draw the group on its own.
+    pub fn group_triangles(&self, name: &str) -> Vec<usize> {
+        match self.groups.get(name) {
+            Some(faces) => faces.iter()
+                .flat_map(|x| self.face_indices[self.faces[*x].triangles.clone()].iter().cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Generate the tangents used for normal mapping from the normals and textures.
+    /// Returns false when the mesh has no textures or normals to generate them from.
+    pub fn generate_tangents(&mut self) -> bool {
+        tangents::generate_tangents(self)
+    }
+}
+
+impl Default for SceneMesh {
+    fn default() -> Self {
+        SceneMesh::new()
+    }
+}
+
 
 /// Stores the information about the node.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SceneNode {
     /// A reference to the parent node.
     pub parent: Option<Index>,
@@ -72,9 +147,12 @@ pub struct SceneNode {
     /// Name to define the give node.
     pub name: String,
     /// Stores transformation of the given node.
+    #[serde(with = "crate::serialize::matrix")]
     pub transformation: Matrix4<f32>,
     /// List of indices to the SceneMesh stored in the SceneGraph
     pub meshes: Vec<usize>,
+    /// Name of the group of faces the node stands for, within the meshes of its object.
+    pub group: Option<String>,
 }
 
 impl SceneNode {
@@ -85,12 +163,19 @@ impl SceneNode {
             parent: None,
             children: Vec::new(),
             meshes: Vec::new(),
+            group: None,
         }
     }
 }
 
+impl Default for SceneNode {
+    fn default() -> Self {
+        SceneNode::new()
+    }
+}
+
 /// The entire graph of the scene along with some data for other points to access.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct SceneGraph {
     /// A root node.
     pub nodes: Vec<SceneNode>,
@@ -98,6 +183,8 @@ pub struct SceneGraph {
     pub meshes: Vec<SceneMesh>,
     /// List of material
     pub materials: Vec<Material>,
+    /// Names of the groups of faces within the meshes.
+    pub groups: Vec<String>,
 }
 
 impl SceneGraph {
@@ -106,7 +193,143 @@ impl SceneGraph {
             nodes: Vec::new(),
             meshes: Vec::new(),
             materials: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Add a node below the parent, linking both of them, and return its index.
+    pub fn add_node(&mut self, name: &str, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        let mut node = SceneNode::new();
+        node.name = name.to_string();
+        node.parent = parent.map(|x| Index { index: x });
+        self.nodes.push(node);
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        index
+    }
+
+    /// Transformation of the node within the world, composed with the ones of its parents.
+    pub fn world_transform(&self, node: usize) -> Matrix4<f32> {
+        let local = self.nodes[node].transformation;
+        match &self.nodes[node].parent {
+            Some(parent) => self.world_transform(parent.index) * local,
+            None => local,
+        }
+    }
+
+    /// World transformations of all the nodes, in the order of the nodes.
+    pub fn world_transforms(&self) -> Vec<Matrix4<f32>> {
+        (0..self.nodes.len()).map(|x| self.world_transform(x)).collect()
+    }
+
+    /// Bounding volumes of the meshes of the node and its descendants, in world space.
+    pub fn node_bounds(&self, node: usize) -> Bounds {
+        Bounds::from_points(&self.world_positions(&[node]))
+    }
+
+    /// Bounding volumes of the whole scene, in world space.
+    pub fn bounds(&self) -> Bounds {
+        Bounds::from_points(&self.world_positions(&self.roots()))
+    }
+
+    /// Recenter and uniformly scale the scene into a unit cube or sphere.
+    /// Returns the applied matrix, whose inverse undoes the change.
+    pub fn normalize(&mut self, volume: FitVolume, mode: Normalization) -> Matrix4<f32> {
+        normalize::normalize(self, volume, mode)
+    }
+
+    /// Write the scene as glTF JSON referring to the binary buffer at `bin_uri`.
+    /// Returns the JSON along with the content of the binary buffer.
+    pub fn to_gltf(&self, bin_uri: &str) -> (String, Vec<u8>) {
+        gltf::to_gltf(self, bin_uri)
+    }
+
+    /// Write the scene as a single GLB buffer.
+    pub fn to_glb(&self) -> Vec<u8> {
+        gltf::to_glb(self)
+    }
+
+    /// Positions of the meshes below the nodes, transformed into world space.
+    fn world_positions(&self, nodes: &[usize]) -> Vec<Vector3<f32>> {
+        let mut positions = Vec::new();
+        for visit in DepthFirst::new(self, nodes) {
+            for mesh in visit.node.meshes.iter() {
+                positions.extend(self.meshes[*mesh].positions().into_iter()
+                    .map(|x| transform_point(&visit.world, x)));
+            }
+        }
+        positions
+    }
+
+    /// Indices of the meshes holding faces of the group.
+    pub fn group_meshes(&self, name: &str) -> Vec<usize> {
+        (0..self.meshes.len()).filter(|x| self.meshes[*x].groups.contains_key(name)).collect()
+    }
+
+    /// Indices of the nodes without a parent.
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.nodes.len()).filter(|x| self.nodes[*x].parent.is_none()).collect()
+    }
+
+    /// Walk every node depth-first from the roots.
+    pub fn depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst::new(self, &self.roots())
+    }
+
+    /// Walk every node breadth-first from the roots.
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst::new(self, &self.roots())
+    }
+
+    /// Index of the first node with the name.
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|x| x.name == name)
+    }
+
+    /// Index of the first mesh with the name.
+    pub fn find_mesh(&self, name: &str) -> Option<usize> {
+        self.meshes.iter().position(|x| x.name == name)
+    }
+
+    /// Index of the first material with the name.
+    pub fn find_material(&self, name: &str) -> Option<usize> {
+        self.materials.iter().position(|x| x.name == name)
+    }
+
+    /// Meshes of the node, without the ones of its children.
+    pub fn node_meshes(&self, node: usize) -> Vec<&SceneMesh> {
+        self.nodes[node].meshes.iter().map(|x| &self.meshes[*x]).collect()
+    }
+
+    /// Materials used by the meshes of the node, each listed once.
+    pub fn node_materials(&self, node: usize) -> Vec<&Material> {
+        let mut indices: Vec<usize> = Vec::new();
+        for mesh in self.node_meshes(node) {
+            if let Some(material) = &mesh.material {
+                if !indices.contains(&material.index) {
+                    indices.push(material.index);
+                }
+            }
         }
+        indices.iter().map(|x| &self.materials[*x]).collect()
+    }
+
+    /// Indices of the parents of the node, from its parent up to the root.
+    pub fn ancestors(&self, node: usize) -> Vec<usize> {
+        let mut ancestors = Vec::new();
+        let mut parent = &self.nodes[node].parent;
+        while let Some(index) = parent {
+            ancestors.push(index.index);
+            parent = &self.nodes[index.index].parent;
+        }
+        ancestors
+    }
+
+    /// Indices of the nodes below the node, depth-first, without the node itself.
+    pub fn descendants(&self, node: usize) -> Vec<usize> {
+        DepthFirst::new(self, &[node]).skip(1).map(|x| x.index).collect()
     }
 
     pub fn owned_nodes(&self) -> Vec<SceneNode> {
@@ -121,3 +344,9 @@ impl SceneGraph {
         self.materials.to_owned()
     }
 }
+
+impl Default for SceneGraph {
+    fn default() -> Self {
+        SceneGraph::new()
+    }
+}
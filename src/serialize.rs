@@ -0,0 +1,106 @@
+//! Helpers to serialize the types that don't implement serde themselves.
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::data::{Material, MaterialLibrary, Model};
+
+/// Serializes the `Matrix4` as its 16 floats in column-major order.
+pub mod matrix {
+    use cgmath::Matrix4;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(matrix: &Matrix4<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+        let values: &[f32; 16] = matrix.as_ref();
+        values.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Matrix4<f32>, D::Error> {
+        let values = <[f32; 16]>::deserialize(deserializer)?;
+        let matrix: &Matrix4<f32> = (&values).into();
+        Ok(*matrix)
+    }
+}
+
+/// Serializes the shared material as its name.
+/// The deserialized material only holds the name, until `Model` links it back to its library.
+pub mod material_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(material: &Rc<RefCell<Material>>, serializer: S)
+                                    -> Result<S::Ok, S::Error> {
+        material.borrow().name.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rc<RefCell<Material>>, D::Error> {
+        let mut material = Material::new();
+        material.name = String::deserialize(deserializer)?;
+        Ok(Rc::new(RefCell::new(material)))
+    }
+}
+
+/// Serializes the shared material as its content.
+pub mod material {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(material: &Rc<RefCell<Material>>, serializer: S)
+                                    -> Result<S::Ok, S::Error> {
+        material.borrow().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rc<RefCell<Material>>, D::Error> {
+        Ok(Rc::new(RefCell::new(Material::deserialize(deserializer)?)))
+    }
+}
+
+/// Serializes the material library as the name of the active material and the materials sorted by name.
+pub mod material_library {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(library: &MaterialLibrary, serializer: S) -> Result<S::Ok, S::Error> {
+        let materials: BTreeMap<&String, Material> = library.1.iter()
+            .map(|(name, material)| (name, material.borrow().clone()))
+            .collect();
+        (&library.0.borrow().name, materials).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MaterialLibrary, D::Error> {
+        let (active, materials) = <(String, BTreeMap<String, Material>)>::deserialize(deserializer)?;
+        let materials: HashMap<String, Rc<RefCell<Material>>> = materials.into_iter()
+            .map(|(name, material)| (name, Rc::new(RefCell::new(material))))
+            .collect();
+        let mut placeholder = Material::new();
+        placeholder.name = active;
+        Ok((Rc::new(RefCell::new(placeholder)), materials))
+    }
+}
+
+impl Serialize for Model {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Model::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Model {
+    /// Deserialize the model and link the materials of its meshes and faces back to the library,
+    /// so they are shared again. The unknown names fall back to the default material.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut model = Model::deserialize(deserializer)?;
+        let library = model.matlib.1.clone();
+        let default = Rc::clone(&model.default_material);
+        let link = |material: &mut Rc<RefCell<Material>>| {
+            let linked = library.get(&material.borrow().name).unwrap_or(&default);
+            *material = Rc::clone(linked);
+        };
+        link(&mut model.matlib.0);
+        for mesh in model.meshes.iter_mut() {
+            link(&mut mesh.material);
+            for face in mesh.faces.iter_mut() {
+                link(&mut face.material);
+            }
+        }
+        Ok(model)
+    }
+}
@@ -0,0 +1,82 @@
+//! Parses the .obj file incrementally while its data arrives in chunks.
+use futures::stream::{Stream, StreamExt};
+
+use crate::error::ObjError;
+use crate::options::LoadOptions;
+use crate::parser::Parser;
+use crate::resolver::ResourceResolver;
+
+/// Feeds the chunks of the file into the parser, one complete line at a time.
+/// The lines may be split anywhere across the chunks.
+pub struct StreamingParser<'a> {
+    parser: Parser,
+    resolver: &'a dyn ResourceResolver,
+    /// Start of the line that is still waiting for the rest of its data.
+    pending: Vec<u8>,
+}
+
+impl<'a> StreamingParser<'a> {
+    /// Create a parser for the file at `file_name`.
+    /// The material libraries are loaded through the resolver, relative to the file.
+    pub fn new(file_name: &str, resolver: &'a dyn ResourceResolver) -> Self {
+        StreamingParser::with_options(file_name, LoadOptions::new(), resolver)
+    }
+
+    /// Create a parser for the file at `file_name` with the given options.
+    pub fn with_options(file_name: &str, options: LoadOptions, resolver: &'a dyn ResourceResolver)
+                        -> Self {
+        StreamingParser {
+            parser: Parser::with_options(file_name, options),
+            resolver,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Parse every line completed by the chunk and keep the rest for the next one.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), ObjError> {
+        let mut start = 0;
+        for end in chunk.iter().enumerate().filter(|(_, x)| **x == b'\n').map(|(i, _)| i) {
+            if self.pending.is_empty() {
+                self.read_line(&chunk[start..end])?;
+            } else {
+                let mut line = std::mem::take(&mut self.pending);
+                line.extend_from_slice(&chunk[start..end]);
+                self.read_line(&line)?;
+            }
+            start = end + 1;
+        }
+        self.pending.extend_from_slice(&chunk[start..]);
+        Ok(())
+    }
+
+    /// Parse the last line, which has no line break, and return the parser holding the model.
+    pub fn finish(mut self) -> Result<Parser, ObjError> {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.read_line(&line)?;
+        }
+        Ok(self.parser)
+    }
+
+    /// Decode a single line, dropping the carriage return of the line break.
+    fn read_line(&mut self, line: &[u8]) -> Result<(), ObjError> {
+        let line = match line.last() {
+            Some(b'\r') => &line[..line.len() - 1],
+            _ => line,
+        };
+        self.parser.read_line(&String::from_utf8_lossy(line), self.resolver)
+    }
+}
+
+/// Parse the file at `file_name` from a stream of its chunks, as they arrive.
+pub async fn parse_stream<S, B>(file_name: &str, mut stream: S, resolver: &dyn ResourceResolver)
+                                -> Result<Parser, ObjError>
+    where S: Stream<Item = B> + Unpin,
+          B: AsRef<[u8]>,
+{
+    let mut parser = StreamingParser::new(file_name, resolver);
+    while let Some(chunk) = stream.next().await {
+        parser.feed(chunk.as_ref())?;
+    }
+    parser.finish()
+}
@@ -0,0 +1,170 @@
+//! Generates the tangents of the meshes for normal mapping.
+//! The tangents are computed by MikkTSpace, so they match the normal maps baked by Blender,
+//! Substance and the other tools following it. The bitangent is `w * cross(normal, tangent)`.
+use std::collections::HashMap;
+
+use bevy_mikktspace::Geometry;
+use cgmath::{InnerSpace, Vector3};
+
+use crate::data::FaceType;
+use crate::scene::SceneMesh;
+
+/// Largest difference between the components of tangents that are still shared by a vertex.
+const TOLERANCE: f32 = 1e-4;
+
+/// Triangles of the mesh as handed to MikkTSpace, which sets the tangent of each corner.
+struct Corners<'a> {
+    mesh: &'a SceneMesh,
+    triangles: &'a [usize],
+    tangents: Vec<[f32; 4]>,
+}
+
+impl Geometry for Corners<'_> {
+    fn num_faces(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        let index = self.triangles[face * 3 + vert];
+        [self.mesh.gv[index * 3], self.mesh.gv[index * 3 + 1], self.mesh.gv[index * 3 + 2]]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        let n = normal(self.mesh, self.triangles[face * 3 + vert]);
+        [n.x, n.y, n.z]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let index = self.triangles[face * 3 + vert];
+        [self.mesh.vt[index * 2], self.mesh.vt[index * 2 + 1]]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent;
+    }
+}
+
+/// Compute the tangent and handedness of each vertex into `mesh.tangents`.
+/// MikkTSpace averages the tangents of the corners sharing the same position, normal and texture,
+/// whether they share a vertex or not. A vertex whose corners get different tangents, such as on
+/// the seam of mirrored texture islands, is split so each side keeps its own.
+/// Returns false, leaving the tangents empty, when the mesh has no textures or normals.
+pub fn generate_tangents(mesh: &mut SceneMesh) -> bool {
+    let count = mesh.gv.len() / 3;
+    mesh.tangents.clear();
+    if count == 0 || mesh.vt.len() != count * 2 || mesh.vn.len() != count * 3 {
+        return false;
+    }
+    let range = mesh.triangles.clone();
+    let triangles: Vec<usize> = mesh.face_indices[range.clone()].to_vec();
+    let generated = {
+        let mut corners = Corners {
+            mesh,
+            triangles: &triangles,
+            tangents: vec![[0.0; 4]; triangles.len()],
+        };
+        if !triangles.is_empty() && !bevy_mikktspace::generate_tangents(&mut corners) {
+            return false;
+        }
+        corners.tangents
+    };
+
+    // Give each corner a vertex with its tangent, splitting the vertices when they differ.
+    let mut tangents: Vec<Option<[f32; 4]>> = vec![None; count];
+    let mut copies: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (corner, tangent) in generated.into_iter().enumerate() {
+        let vertex = triangles[corner];
+        let target = match tangents[vertex] {
+            None => vertex,
+            Some(shared) if same_tangent(shared, tangent) => vertex,
+            Some(_) => {
+                let copy = copies.get(&vertex)
+                    .and_then(|x| x.iter().find(|x| tangents[**x].is_some_and(|t| same_tangent(t, tangent))));
+                match copy {
+                    Some(copy) => *copy,
+                    None => {
+                        let copy = duplicate_vertex(mesh, vertex);
+                        tangents.push(None);
+                        copies.entry(vertex).or_default().push(copy);
+                        copy
+                    },
+                }
+            },
+        };
+        tangents[target] = Some(tangent);
+        mesh.face_indices[range.start + corner] = target;
+    }
+
+    // Triangle faces hold their triangles one after the other, so they take the remapped
+    // triangles, while the corners of the polygons take the vertex of their first triangle.
+    if !copies.is_empty() {
+        let mut faces = std::mem::take(&mut mesh.faces);
+        for face in faces.iter_mut() {
+            match face.face_type {
+                FaceType::Triangle => face.indices = mesh.face_indices[face.triangles.clone()].to_vec(),
+                FaceType::Polygon => {
+                    let mut remapped: HashMap<usize, usize> = HashMap::new();
+                    for i in face.triangles.clone() {
+                        remapped.entry(triangles[i - range.start]).or_insert(mesh.face_indices[i]);
+                    }
+                    for vertex in face.indices.iter_mut() {
+                        *vertex = *remapped.get(vertex).unwrap_or(vertex);
+                    }
+                },
+                _ => {},
+            }
+        }
+        mesh.faces = faces;
+    }
+
+    // The vertices only used by lines and points get any tangent perpendicular to their normal.
+    for (vertex, tangent) in tangents.into_iter().enumerate() {
+        let tangent = tangent.unwrap_or_else(|| {
+            let t = perpendicular(normal(mesh, vertex)).normalize();
+            [t.x, t.y, t.z, 1.0]
+        });
+        mesh.tangents.extend_from_slice(&tangent);
+    }
+    true
+}
+
+/// True, if the tangents are close enough for the corners to share a vertex.
+fn same_tangent(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[3] == b[3] && a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() <= TOLERANCE)
+}
+
+/// Copy every channel of the vertex into a new vertex and return its index.
+fn duplicate_vertex(mesh: &mut SceneMesh, vertex: usize) -> usize {
+    fn copy(values: &mut Vec<f32>, vertex: usize, size: usize) {
+        if values.len() >= (vertex + 1) * size {
+            let copied: Vec<f32> = values[vertex * size..(vertex + 1) * size].to_vec();
+            values.extend_from_slice(&copied);
+        }
+    }
+    let index = mesh.gv.len() / 3;
+    copy(&mut mesh.gv, vertex, 3);
+    copy(&mut mesh.vn, vertex, 3);
+    copy(&mut mesh.vt, vertex, 2);
+    copy(&mut mesh.colors, vertex, 4);
+    index
+}
+
+/// Any vector perpendicular to the normal, used when no triangle defines the tangent.
+fn perpendicular(normal: Vector3<f32>) -> Vector3<f32> {
+    let axis = if normal.x.abs() < 0.9 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+    let tangent = axis - normal * normal.dot(axis);
+    if tangent.magnitude2() > 0.0 { tangent } else { axis }
+}
+
+fn normal(mesh: &SceneMesh, index: usize) -> Vector3<f32> {
+    let n = Vector3::new(mesh.vn[index * 3], mesh.vn[index * 3 + 1], mesh.vn[index * 3 + 2]);
+    if n.magnitude2() > 0.0 { n.normalize() } else { n }
+}
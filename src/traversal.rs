@@ -0,0 +1,82 @@
+//! Iterators walking the nodes of the scene graph from its roots.
+use std::collections::VecDeque;
+
+use cgmath::Matrix4;
+
+use crate::scene::{SceneGraph, SceneNode};
+
+/// Node reached by the traversal, along with its place in the graph.
+#[derive(Debug, Clone)]
+pub struct Visit<'a> {
+    /// Index of the node in the graph.
+    pub index: usize,
+    /// The node itself.
+    pub node: &'a SceneNode,
+    /// Number of levels below the nodes the walk started from, 0 for those nodes.
+    pub depth: usize,
+    /// Transformation of the node within the world.
+    pub world: Matrix4<f32>,
+}
+
+/// Nodes still to be visited, with their depth and the world transformation of their parent.
+type Pending = (usize, usize, Matrix4<f32>);
+
+/// Visits the nodes depth-first, each node before its children.
+pub struct DepthFirst<'a> {
+    graph: &'a SceneGraph,
+    stack: Vec<Pending>,
+}
+
+impl<'a> DepthFirst<'a> {
+    /// Walk the graph from the given nodes, in their order.
+    pub fn new(graph: &'a SceneGraph, start: &[usize]) -> Self {
+        let stack = start.iter().rev().map(|x| (*x, 0, parent_world(graph, *x))).collect();
+        DepthFirst { graph, stack }
+    }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Visit<'a>> {
+        let (index, depth, parent) = self.stack.pop()?;
+        let node = &self.graph.nodes[index];
+        let world = parent * node.transformation;
+        self.stack.extend(node.children.iter().rev().map(|x| (*x, depth + 1, world)));
+        Some(Visit { index, node, depth, world })
+    }
+}
+
+/// Visits the nodes breadth-first, level by level.
+pub struct BreadthFirst<'a> {
+    graph: &'a SceneGraph,
+    queue: VecDeque<Pending>,
+}
+
+impl<'a> BreadthFirst<'a> {
+    /// Walk the graph from the given nodes, in their order.
+    pub fn new(graph: &'a SceneGraph, start: &[usize]) -> Self {
+        let queue = start.iter().map(|x| (*x, 0, parent_world(graph, *x))).collect();
+        BreadthFirst { graph, queue }
+    }
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Visit<'a>> {
+        let (index, depth, parent) = self.queue.pop_front()?;
+        let node = &self.graph.nodes[index];
+        let world = parent * node.transformation;
+        self.queue.extend(node.children.iter().map(|x| (*x, depth + 1, world)));
+        Some(Visit { index, node, depth, world })
+    }
+}
+
+/// World transformation of the parent of the node, so walks may start anywhere.
+fn parent_world(graph: &SceneGraph, node: usize) -> Matrix4<f32> {
+    match &graph.nodes[node].parent {
+        Some(parent) => graph.world_transform(parent.index),
+        None => Matrix4::from_scale(1.0),
+    }
+}
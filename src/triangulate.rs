@@ -0,0 +1,138 @@
+//! Triangulates the polygons of the faces by ear clipping.
+//! The polygons are projected onto their best-fit plane, so they may be concave or slightly non-planar.
+use cgmath::{InnerSpace, Vector2, Vector3};
+
+use crate::data::{Face, FaceType, Model};
+
+/// Smallest area that is still considered to turn a corner, relative to the squared extent of the polygon.
+const EPSILON: f32 = 1e-10;
+
+/// Triangles of the face, as indices of its corners.
+/// Triangulated faces are split in groups of three, polygons are ear clipped.
+pub fn face_triangles(model: &Model, face: &Face) -> Vec<[usize; 3]> {
+    match face.face_type {
+        FaceType::Polygon => polygon_triangles(model, &face.vertices),
+        FaceType::Triangle => (0..face.vertices.len() / 3)
+            .map(|x| [x * 3, x * 3 + 1, x * 3 + 2])
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Triangulate the polygon made of the positions of the model at `vertices`.
+pub fn polygon_triangles(model: &Model, vertices: &[usize]) -> Vec<[usize; 3]> {
+    let polygon: Vec<Vector3<f32>> = vertices.iter()
+        .map(|v| Vector3::new(model.gv[v * 3], model.gv[v * 3 + 1], model.gv[v * 3 + 2]))
+        .collect();
+    triangulate(&polygon)
+}
+
+/// Normal of the polygon by Newell's method, not normalized so its length is twice the area.
+pub fn polygon_normal(polygon: &[Vector3<f32>]) -> Vector3<f32> {
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    normal
+}
+
+/// Split the polygon into triangles, returned as indices of its corners in their original winding.
+/// Duplicate and collinear corners are left out of the triangles, as they add no area.
+/// Degenerate polygons without any area are split into a fan.
+pub fn triangulate(polygon: &[Vector3<f32>]) -> Vec<[usize; 3]> {
+    let count = polygon.len();
+    if count < 3 {
+        return Vec::new();
+    }
+    // The tolerances scale with the polygon, so small parts are triangulated like large ones.
+    let origin = polygon[0];
+    let extent = polygon.iter()
+        .map(|p| (p - origin).magnitude2())
+        .fold(0.0, f32::max);
+    let tolerance = EPSILON * extent;
+    let normal = polygon_normal(polygon);
+    if count == 3 || normal.magnitude() <= tolerance {
+        return (1..count - 1).map(|x| [0, x, x + 1]).collect();
+    }
+    let points = project(polygon, normal.normalize());
+
+    // Skip the corners at the same place as the previous one.
+    let mut remaining: Vec<usize> = Vec::with_capacity(count);
+    for i in 0..count {
+        if remaining.last().is_none_or(|x| (points[i] - points[*x]).magnitude2() > tolerance) {
+            remaining.push(i);
+        }
+    }
+    while remaining.len() > 1
+        && (points[remaining[0]] - points[remaining[remaining.len() - 1]]).magnitude2() <= tolerance {
+        remaining.pop();
+    }
+
+    let mut triangles = Vec::with_capacity(count - 2);
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let corner = |i: usize| {
+            (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len])
+        };
+        // Collinear corners are dropped, the rest is clipped at the first ear.
+        let collinear = (0..len).find(|i| {
+            let (a, b, c) = corner(*i);
+            cross(points[a], points[b], points[c]).abs() <= tolerance
+        });
+        if let Some(i) = collinear {
+            remaining.remove(i);
+            continue;
+        }
+        // Starting after the first corner splits the convex polygons into a fan from it.
+        let order = (1..len).chain(0..1);
+        let ear = order.clone().find(|i| is_ear(&points, &remaining, corner(*i), tolerance))
+            .or_else(|| order.clone().find(|i| {
+                let (a, b, c) = corner(*i);
+                cross(points[a], points[b], points[c]) > 0.0
+            }))
+            .unwrap_or(0);
+        let (a, b, c) = corner(ear);
+        triangles.push([a, b, c]);
+        remaining.remove(ear);
+    }
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    triangles
+}
+
+/// Project the polygon onto the plane with the given unit normal, relative to its first corner.
+/// The projected polygon winds counter-clockwise.
+fn project(polygon: &[Vector3<f32>], normal: Vector3<f32>) -> Vec<Vector2<f32>> {
+    let axis = if normal.x.abs() < 0.9 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+    let u = (axis - normal * normal.dot(axis)).normalize();
+    let v = normal.cross(u);
+    let origin = polygon[0];
+    polygon.iter().map(|p| p - origin).map(|p| Vector2::new(p.dot(u), p.dot(v))).collect()
+}
+
+/// Twice the signed area of the triangle, positive when it turns counter-clockwise.
+fn cross(a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+/// True, if the convex corner `b` can be clipped without covering any other remaining corner.
+fn is_ear(points: &[Vector2<f32>], remaining: &[usize], (a, b, c): (usize, usize, usize),
+          tolerance: f32) -> bool {
+    let (pa, pb, pc) = (points[a], points[b], points[c]);
+    if cross(pa, pb, pc) <= tolerance {
+        return false;
+    }
+    !remaining.iter()
+        .filter(|x| **x != a && **x != b && **x != c)
+        .map(|x| points[*x])
+        .filter(|p| *p != pa && *p != pb && *p != pc)
+        .any(|p| cross(pa, pb, p) >= 0.0 && cross(pb, pc, p) >= 0.0 && cross(pc, pa, p) >= 0.0)
+}
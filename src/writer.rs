@@ -0,0 +1,424 @@
+//! Writes the parsed model, or the scene, back out as .obj text along with its .mtl library.
+use std::fmt::Write;
+use std::rc::Rc;
+
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector3};
+
+use crate::bounds::transform_point;
+use crate::data::*;
+use crate::scene::{SceneGraph, SceneMesh};
+use crate::triangulate::face_triangles;
+
+/// Writes the .obj and .mtl text.
+pub struct ObjWriter {
+    /// Largest number of decimals of the written floats.
+    pub precision: usize,
+    /// Refer to the vertices relative to the last one written, with negative indices.
+    pub relative_indices: bool,
+    /// Write the faces kept as polygons, such as quads, as they are instead of their triangles.
+    pub keep_polygons: bool,
+}
+
+/// Attributes written before the faces, only written again when they change.
+struct State {
+    groups: Vec<String>,
+    smoothing_group: u32,
+    merging_group: u32,
+}
+
+/// Number of positions, textures and normals written so far.
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    positions: usize,
+    textures: usize,
+    normals: usize,
+}
+
+impl ObjWriter {
+    /// Writer with 6 decimals, absolute indices, keeping the polygons.
+    pub fn new() -> Self {
+        ObjWriter {
+            precision: 6,
+            relative_indices: false,
+            keep_polygons: true,
+        }
+    }
+
+    /// Write the model, referring to the material library at `mtl_file`.
+    /// Returns the .obj and the .mtl text.
+    pub fn write_model(&self, model: &Model, mtl_file: &str) -> (String, String) {
+        let mut materials: Vec<Rc<std::cell::RefCell<Material>>> =
+            model.matlib.1.values().cloned().collect();
+        materials.sort_by(|a, b| a.borrow().name.cmp(&b.borrow().name));
+        if model.meshes.iter().any(|x| Rc::ptr_eq(&x.material, &model.default_material)) {
+            materials.push(Rc::clone(&model.default_material));
+        }
+        let materials: Vec<Material> = materials.iter().map(|x| x.borrow().clone()).collect();
+
+        let mut obj = String::new();
+        self.write_header(&mut obj, &materials, mtl_file);
+        let has_colors = !model.colors.is_empty();
+        for (i, v) in model.gv.chunks(3).enumerate() {
+            let color = if has_colors { &model.colors[i * 4..i * 4 + 4] } else { &[] };
+            self.write_position(&mut obj, &[v[0], v[1], v[2]], color);
+        }
+        for vt in model.vt.chunks(2) {
+            writeln!(obj, "vt {} {}", self.float(vt[0]), self.float(vt[1])).unwrap();
+        }
+        for vn in model.vn.chunks(3) {
+            writeln!(obj, "vn {}", self.floats(vn)).unwrap();
+        }
+        let counts = Counts {
+            positions: model.gv.len() / 3,
+            textures: model.vt.len() / 2,
+            normals: model.vn.len() / 3,
+        };
+
+        let mut state = State { groups: Vec::new(), smoothing_group: 0, merging_group: 0 };
+        for object in model.objects.iter() {
+            writeln!(obj, "o {}", object.name).unwrap();
+            for mesh in object.meshes.iter().map(|x| &model.meshes[*x]) {
+                writeln!(obj, "usemtl {}", mesh.material.borrow().name).unwrap();
+                for face in mesh.faces.iter() {
+                    let groups: Vec<String> = face.groups.iter()
+                        .map(|x| model.grplib.1[*x].clone())
+                        .collect();
+                    let resolution = model.merging_resolutions.get(&face.merging_group).cloned();
+                    self.write_state(&mut obj, &mut state, groups, face.smoothing_group,
+                                     face.merging_group, resolution);
+                    let corner = |i: usize| {
+                        (face.vertices[i], face.textures.get(i).cloned(), face.normals.get(i).cloned())
+                    };
+                    let polygons: Vec<Vec<usize>> = match face.face_type {
+                        FaceType::Triangle => (0..face.vertices.len() / 3)
+                            .map(|x| vec![x * 3, x * 3 + 1, x * 3 + 2])
+                            .collect(),
+                        FaceType::Polygon if !self.keep_polygons => face_triangles(model, face)
+                            .iter()
+                            .map(|x| x.to_vec())
+                            .collect(),
+                        _ => vec![(0..face.vertices.len()).collect()],
+                    };
+                    for polygon in polygons {
+                        let corners: Vec<_> = polygon.into_iter().map(corner).collect();
+                        self.write_face(&mut obj, face.face_type, &corners, counts);
+                    }
+                }
+            }
+        }
+        (obj, self.write_mtl(&materials))
+    }
+
+    /// Write the scene, referring to the material library at `mtl_file`.
+    /// Each node with meshes becomes an object, named after the path of the node below the root.
+    /// The world transformations are applied to the positions and normals.
+    /// Returns the .obj and the .mtl text.
+    pub fn write_scene(&self, scene: &SceneGraph, mtl_file: &str) -> (String, String) {
+        let mut materials = scene.materials.clone();
+        materials.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut obj = String::new();
+        self.write_header(&mut obj, &materials, mtl_file);
+        let mut counts = Counts::default();
+        let mut state = State { groups: Vec::new(), smoothing_group: 0, merging_group: 0 };
+        for visit in scene.depth_first().filter(|x| !x.node.meshes.is_empty()) {
+            let mut names: Vec<&str> = scene.ancestors(visit.index).iter().rev()
+                .map(|x| scene.nodes[*x].name.as_str())
+                .collect();
+            names.push(&visit.node.name);
+            let name: Vec<&str> = names.into_iter().filter(|x| !x.is_empty()).collect();
+            writeln!(obj, "o {}", name.join("/")).unwrap();
+            for mesh in visit.node.meshes.iter().map(|x| &scene.meshes[*x]) {
+                let normals = self.write_vertices(&mut obj, mesh, &visit.world);
+                if let Some(material) = &mesh.material {
+                    writeln!(obj, "usemtl {}", scene.materials[material.index].name).unwrap();
+                }
+                // The vertices of the mesh follow the ones written before it.
+                let offset = counts;
+                counts.positions += mesh.gv.len() / 3;
+                counts.textures += mesh.vt.len() / 2;
+                counts.normals += normals.iter().flatten().count();
+                let has_textures = !mesh.vt.is_empty();
+                let corner = |i: usize| (
+                    offset.positions + i,
+                    if has_textures { Some(offset.textures + i) } else { None },
+                    normals.get(i).cloned().flatten().map(|x| offset.normals + x),
+                );
+                for (index, face) in mesh.faces.iter().enumerate() {
+                    let groups: Vec<String> = scene.groups.iter()
+                        .filter(|x| mesh.groups.get(*x).is_some_and(|faces| faces.contains(&index)))
+                        .cloned()
+                        .collect();
+                    self.write_state(&mut obj, &mut state, groups, face.smoothing_group,
+                                     face.merging_group, None);
+                    let polygons: Vec<&[usize]> = match face.face_type {
+                        FaceType::Triangle => face.indices.chunks(3).collect(),
+                        FaceType::Polygon if !self.keep_polygons => {
+                            mesh.face_indices[face.triangles.clone()].chunks(3).collect()
+                        },
+                        _ => vec![&face.indices[..]],
+                    };
+                    for polygon in polygons {
+                        let corners: Vec<_> = polygon.iter().map(|x| corner(*x)).collect();
+                        self.write_face(&mut obj, face.face_type, &corners, counts);
+                    }
+                }
+            }
+        }
+        (obj, self.write_mtl(&materials))
+    }
+
+    /// Write the materials as a .mtl library.
+    pub fn write_mtl(&self, materials: &[Material]) -> String {
+        let mut mtl = String::new();
+        for material in materials {
+            if !mtl.is_empty() {
+                mtl.push('\n');
+            }
+            writeln!(mtl, "newmtl {}", material.name).unwrap();
+            writeln!(mtl, "Ka {}", self.floats(&material.ambient)).unwrap();
+            writeln!(mtl, "Kd {}", self.floats(&material.diffuse)).unwrap();
+            writeln!(mtl, "Ks {}", self.floats(&material.specular)).unwrap();
+            if material.emissive != [0.0; 3] {
+                writeln!(mtl, "Ke {}", self.floats(&material.emissive)).unwrap();
+            }
+            if material.transmission != [0.0; 3] {
+                writeln!(mtl, "Tf {}", self.floats(&material.transmission)).unwrap();
+            }
+            writeln!(mtl, "Ns {}", self.float(material.shininess)).unwrap();
+            writeln!(mtl, "Ni {}", self.float(material.ri)).unwrap();
+            let halo = if material.dissolve_halo { "-halo " } else { "" };
+            writeln!(mtl, "d {}{}", halo, self.float(material.dissolve)).unwrap();
+            if let Some(illumination) = material.illumination {
+                writeln!(mtl, "illum {}", illumination).unwrap();
+            }
+            if let Some(pbr) = &material.pbr {
+                let scalars = [
+                    ("Pr", pbr.roughness), ("Pm", pbr.metallic), ("Ps", pbr.sheen),
+                    ("Pc", pbr.clearcoat_thickness), ("Pcr", pbr.clearcoat_roughness),
+                    ("aniso", pbr.anisotropy), ("anisor", pbr.anisotropy_rotation),
+                ];
+                for (keyword, value) in scalars.iter() {
+                    writeln!(mtl, "{} {}", keyword, self.float(*value)).unwrap();
+                }
+                let maps = [
+                    ("map_Pr", &pbr.texture_roughness), ("map_Pm", &pbr.texture_metallic),
+                    ("map_Ps", &pbr.texture_sheen), ("map_RMA", &pbr.texture_rma),
+                ];
+                for (keyword, map) in maps.iter() {
+                    self.write_texture(&mut mtl, keyword, map);
+                }
+            }
+            let maps = [
+                ("map_Ka", &material.texture_ambient), ("map_Kd", &material.texture_diffuse),
+                ("map_Ks", &material.texture_specular), ("map_Ke", &material.texture_emissive),
+                ("map_Ns", &material.texture_shininess), ("map_d", &material.texture_dissolve),
+                ("bump", &material.texture_bump), ("norm", &material.texture_normal),
+                ("disp", &material.texture_displacement), ("decal", &material.texture_decal),
+            ];
+            for (keyword, map) in maps.iter() {
+                self.write_texture(&mut mtl, keyword, map);
+            }
+        }
+        mtl
+    }
+
+    /// Write the material library, if there are materials to refer to.
+    fn write_header(&self, obj: &mut String, materials: &[Material], mtl_file: &str) {
+        if !materials.is_empty() && !mtl_file.is_empty() {
+            writeln!(obj, "mtllib {}", mtl_file).unwrap();
+        }
+    }
+
+    /// Write the positions, textures and normals of the mesh, in world space.
+    /// The zero normals, given to the corners of points and lines, are left out.
+    /// Returns the index of the written normal of each vertex, among the normals of the mesh.
+    fn write_vertices(&self, obj: &mut String, mesh: &SceneMesh, world: &Matrix4<f32>) -> Vec<Option<usize>> {
+        let identity = *world == Matrix4::identity();
+        let linear = Matrix3::from_cols(world.x.truncate(), world.y.truncate(), world.z.truncate());
+        let normal_matrix = linear.invert().map(|x| x.transpose()).unwrap_or(linear);
+        let has_colors = mesh.colors.len() == mesh.gv.len() / 3 * 4;
+        for (i, v) in mesh.gv.chunks(3).enumerate() {
+            let mut p = Vector3::new(v[0], v[1], v[2]);
+            if !identity {
+                p = transform_point(world, p);
+            }
+            let color = if has_colors { &mesh.colors[i * 4..i * 4 + 4] } else { &[] };
+            self.write_position(obj, &[p.x, p.y, p.z], color);
+        }
+        for vt in mesh.vt.chunks(2) {
+            writeln!(obj, "vt {} {}", self.float(vt[0]), self.float(vt[1])).unwrap();
+        }
+        let mut written = 0;
+        let mut normals = Vec::with_capacity(mesh.vn.len() / 3);
+        for vn in mesh.vn.chunks(3) {
+            let mut n = Vector3::new(vn[0], vn[1], vn[2]);
+            if n.magnitude2() == 0.0 {
+                normals.push(None);
+                continue;
+            }
+            if !identity {
+                n = (normal_matrix * n).normalize();
+            }
+            writeln!(obj, "vn {}", self.floats(&[n.x, n.y, n.z])).unwrap();
+            normals.push(Some(written));
+            written += 1;
+        }
+        normals
+    }
+
+    /// Write the position, with its color if it has one. The alpha is left out when opaque.
+    fn write_position(&self, obj: &mut String, position: &[f32; 3], color: &[f32]) {
+        write!(obj, "v {}", self.floats(position)).unwrap();
+        match color {
+            [r, g, b, a] if *a == 1.0 => write!(obj, " {}", self.floats(&[*r, *g, *b])).unwrap(),
+            [_, _, _, _] => write!(obj, " {}", self.floats(color)).unwrap(),
+            _ => {},
+        }
+        obj.push('\n');
+    }
+
+    /// Write the groups, smoothing group and merging group of the next face, where they changed.
+    fn write_state(&self, obj: &mut String, state: &mut State, groups: Vec<String>,
+                   smoothing_group: u32, merging_group: u32, resolution: Option<f32>) {
+        if groups != state.groups {
+            if groups.is_empty() {
+                obj.push_str("g\n");
+            } else {
+                writeln!(obj, "g {}", groups.join(" ")).unwrap();
+            }
+            state.groups = groups;
+        }
+        if smoothing_group != state.smoothing_group {
+            match smoothing_group {
+                0 => obj.push_str("s off\n"),
+                x => writeln!(obj, "s {}", x).unwrap(),
+            }
+            state.smoothing_group = smoothing_group;
+        }
+        if merging_group != state.merging_group {
+            match (merging_group, resolution) {
+                (0, _) => obj.push_str("mg off\n"),
+                (x, Some(resolution)) => writeln!(obj, "mg {} {}", x, self.float(resolution)).unwrap(),
+                (x, None) => writeln!(obj, "mg {}", x).unwrap(),
+            }
+            state.merging_group = merging_group;
+        }
+    }
+
+    /// Write the face from its corners, as the position, texture and normal indices.
+    /// Points only refer to positions and lines to positions and textures, as in the .obj format.
+    /// The textures and normals are only written when every corner has them.
+    fn write_face(&self, obj: &mut String, face_type: FaceType,
+                  corners: &[(usize, Option<usize>, Option<usize>)], counts: Counts) {
+        obj.push_str(match face_type {
+            FaceType::Point => "p",
+            FaceType::Line => "l",
+            _ => "f",
+        });
+        let has_textures = face_type != FaceType::Point && corners.iter().all(|x| x.1.is_some());
+        let has_normals = face_type != FaceType::Point && face_type != FaceType::Line
+            && corners.iter().all(|x| x.2.is_some());
+        for (v, vt, vn) in corners.iter() {
+            let vt = if has_textures { *vt } else { None };
+            let vn = if has_normals { *vn } else { None };
+            write!(obj, " {}", self.index(*v, counts.positions)).unwrap();
+            match (vt, vn) {
+                (Some(vt), Some(vn)) => write!(obj, "/{}/{}", self.index(vt, counts.textures),
+                                               self.index(vn, counts.normals)).unwrap(),
+                (Some(vt), None) => write!(obj, "/{}", self.index(vt, counts.textures)).unwrap(),
+                (None, Some(vn)) => write!(obj, "//{}", self.index(vn, counts.normals)).unwrap(),
+                (None, None) => {},
+            }
+        }
+        obj.push('\n');
+    }
+
+    /// Write the texture map with the options that differ from their defaults.
+    fn write_texture(&self, mtl: &mut String, keyword: &str, map: &Option<TextureMap>) {
+        let map = match map {
+            Some(map) => map,
+            None => return,
+        };
+        let default = TextureMap::new(&map.path);
+        mtl.push_str(keyword);
+        let vectors = [
+            ("-o", map.offset, default.offset),
+            ("-s", map.scale, default.scale),
+            ("-t", map.turbulence, default.turbulence),
+        ];
+        for (option, value, default) in vectors.iter() {
+            if value != default {
+                write!(mtl, " {} {}", option, self.floats(value)).unwrap();
+            }
+        }
+        let flags = [
+            ("-clamp", map.clamp, default.clamp),
+            ("-blendu", map.blend_u, default.blend_u),
+            ("-blendv", map.blend_v, default.blend_v),
+            ("-cc", map.color_correction, default.color_correction),
+        ];
+        for (option, value, default) in flags.iter() {
+            if value != default {
+                write!(mtl, " {} {}", option, if *value { "on" } else { "off" }).unwrap();
+            }
+        }
+        if map.bump_multiplier != default.bump_multiplier {
+            write!(mtl, " -bm {}", self.float(map.bump_multiplier)).unwrap();
+        }
+        if map.base != default.base || map.gain != default.gain {
+            write!(mtl, " -mm {} {}", self.float(map.base), self.float(map.gain)).unwrap();
+        }
+        if let Some(channel) = map.channel {
+            let channel = match channel {
+                TextureChannel::Red => "r",
+                TextureChannel::Green => "g",
+                TextureChannel::Blue => "b",
+                TextureChannel::Matte => "m",
+                TextureChannel::Luminance => "l",
+                TextureChannel::Depth => "z",
+            };
+            write!(mtl, " -imfchan {}", channel).unwrap();
+        }
+        if let Some(resolution) = map.resolution {
+            write!(mtl, " -texres {}", resolution).unwrap();
+        }
+        if let Some(boost) = map.boost {
+            write!(mtl, " -boost {}", self.float(boost)).unwrap();
+        }
+        writeln!(mtl, " {}", map.path).unwrap();
+    }
+
+    /// Index of the element as written, 1-based or relative to the elements written so far.
+    fn index(&self, index: usize, count: usize) -> String {
+        if self.relative_indices {
+            format!("-{}", count - index)
+        } else {
+            (index + 1).to_string()
+        }
+    }
+
+    /// The floats separated by spaces.
+    fn floats(&self, values: &[f32]) -> String {
+        values.iter().map(|x| self.float(*x)).collect::<Vec<_>>().join(" ")
+    }
+
+    /// The float with at most `precision` decimals, without the trailing zeros.
+    fn float(&self, value: f32) -> String {
+        let text = format!("{:.*}", self.precision, value);
+        let text = if text.contains('.') {
+            text.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            &text
+        };
+        match text {
+            "-0" => "0".to_string(),
+            _ => text.to_string(),
+        }
+    }
+}
+
+impl Default for ObjWriter {
+    fn default() -> Self {
+        ObjWriter::new()
+    }
+}
@@ -0,0 +1,85 @@
+//! Test suite for the bounding volumes of the meshes and the scene.
+
+extern crate cgmath;
+extern crate obj;
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use obj::bounds::{transform_point, Aabb, Bounds, OrientedBox};
+use obj::normalize::{FitVolume, Normalization};
+use obj::reader::ObjReader;
+
+fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    (a - b).magnitude() < 1e-4
+}
+
+#[test]
+fn fits_oriented_box_to_rotated_points() {
+    // Box of 4 x 1 x 0.5, turned 45 degrees around z.
+    let (c, s) = (0.5f32.sqrt(), 0.5f32.sqrt());
+    let mut points = Vec::new();
+    for &x in &[-2.0, 2.0] {
+        for &y in &[-0.5, 0.5] {
+            for &z in &[-0.25, 0.25] {
+                points.push(Vector3::new(x * c - y * s + 1.0, x * s + y * c, z));
+            }
+        }
+    }
+    let oriented = OrientedBox::from_points(&points);
+    assert!(close(oriented.center, Vector3::new(1.0, 0.0, 0.0)));
+    assert!(close(oriented.half_extents, Vector3::new(2.0, 0.5, 0.25)));
+    assert!((oriented.axes[0].dot(Vector3::new(c, s, 0.0)).abs() - 1.0).abs() < 1e-4);
+
+    let aabb = Aabb::from_points(&points);
+    assert!(close(aabb.center(), Vector3::new(1.0, 0.0, 0.0)));
+    assert!(aabb.size().x > 2.0 * 2.0 * c);
+    assert!(Bounds::new().aabb.is_empty() && Bounds::new().sphere.is_empty());
+}
+
+#[test]
+fn bounds_meshes_nodes_and_scene() {
+    let data = "v 0 0 0\nv 2 0 0\nv 0 2 0\no a\nf 1 2 3\no b\nf 1 2 3\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, "").unwrap();
+    let scene = &mut reader.scene;
+    let mesh = scene.meshes[0].bounds();
+    assert_eq!(mesh.aabb.min, Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(mesh.aabb.max, Vector3::new(2.0, 2.0, 0.0));
+    assert!(close(mesh.sphere.center, Vector3::new(1.0, 1.0, 0.0)));
+    assert!((mesh.sphere.radius - 2.0f32.sqrt()).abs() < 1e-5);
+
+    let b = scene.find_node("b").unwrap();
+    scene.nodes[b].transformation = Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0));
+    scene.nodes[0].transformation = Matrix4::from_scale(2.0);
+    let node = scene.node_bounds(b);
+    assert_eq!(node.aabb.min, Vector3::new(20.0, 0.0, 0.0));
+    assert_eq!(node.aabb.max, Vector3::new(24.0, 4.0, 0.0));
+    let all = scene.bounds();
+    assert_eq!(all.aabb.min, Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(all.aabb.max, Vector3::new(24.0, 4.0, 0.0));
+    assert_eq!(mesh.aabb.transformed(&Matrix4::from_scale(2.0)).max, Vector3::new(4.0, 4.0, 0.0));
+}
+
+#[test]
+fn normalizes_into_unit_cube_and_sphere() {
+    let data = "v 1000 0 0\nv 3000 0 0\nv 1000 1000 0\nf 1 2 3\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, "").unwrap();
+    let scene = &mut reader.scene;
+    let matrix = scene.normalize(FitVolume::Cube, Normalization::Transform);
+    let bounds = scene.bounds();
+    assert!(close(bounds.aabb.min, Vector3::new(-0.5, -0.25, 0.0)));
+    assert!(close(bounds.aabb.max, Vector3::new(0.5, 0.25, 0.0)));
+    assert_eq!(scene.meshes[0].gv[0], 1000.0);
+    assert_eq!(scene.nodes[0].transformation, matrix);
+
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, "").unwrap();
+    let scene = &mut reader.scene;
+    let matrix = scene.normalize(FitVolume::Sphere, Normalization::Bake);
+    let bounds = scene.bounds();
+    assert!(close(bounds.sphere.center, Vector3::new(0.0, 0.0, 0.0)));
+    assert!((bounds.sphere.radius - 1.0).abs() < 1e-5);
+    let undone = transform_point(&matrix.invert().unwrap(), Vector3::new(
+        scene.meshes[0].gv[3], scene.meshes[0].gv[4], scene.meshes[0].gv[5]));
+    assert!(close(undone, Vector3::new(3000.0, 0.0, 0.0)));
+}
@@ -0,0 +1,86 @@
+//! Test suite for exporting the scene as glTF.
+
+extern crate obj;
+extern crate serde_json;
+
+use obj::reader::ObjReader;
+use serde_json::Value;
+
+const DATA: &str = "mtllib scene.mtl\nv 0 0 0\nv 2 0 0\nv 2 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                    vn 0 0 1\no floor\nusemtl tiles\nf 1/1/1 2/2/1 3/3/1 4/4/1\nl 1 2 3\no car/wheel\np 4\n";
+
+const MATERIAL: &str = "newmtl tiles\nKd 0.5 0.5 0.5\nNs 0\nd 0.5\nmap_Kd floor tiles.png\n";
+
+fn read() -> ObjReader {
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(DATA, MATERIAL).unwrap();
+    reader
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[test]
+fn exports_nodes_meshes_and_materials() {
+    let reader = read();
+    let (json, bin) = reader.scene.to_gltf("scene.bin");
+    let gltf: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(gltf["asset"]["version"], "2.0");
+    assert_eq!(gltf["scenes"][0]["nodes"], serde_json::json!([0]));
+    assert_eq!(gltf["nodes"][1]["name"], "floor");
+    assert_eq!(gltf["nodes"][2]["children"], serde_json::json!([3]));
+    assert_eq!(gltf["buffers"][0]["uri"], "scene.bin");
+    assert_eq!(gltf["buffers"][0]["byteLength"], bin.len());
+    assert_eq!(bin.len() % 4, 0);
+
+    let floor = &gltf["meshes"][gltf["nodes"][1]["mesh"].as_u64().unwrap() as usize];
+    let primitives = floor["primitives"].as_array().unwrap();
+    assert_eq!(primitives.iter().map(|x| x["mode"].as_u64().unwrap()).collect::<Vec<_>>(), vec![4, 1]);
+    let attributes = &primitives[0]["attributes"];
+    let position = &gltf["accessors"][attributes["POSITION"].as_u64().unwrap() as usize];
+    assert_eq!(position["count"], 9);
+    assert_eq!(position["min"], serde_json::json!([0.0, 0.0, 0.0]));
+    assert_eq!(position["max"], serde_json::json!([2.0, 1.0, 0.0]));
+    assert!(attributes.get("NORMAL").is_some() && attributes.get("TEXCOORD_0").is_some());
+    let lines = &gltf["accessors"][primitives[1]["indices"].as_u64().unwrap() as usize];
+    assert_eq!(lines["count"], 4);
+
+    // The first uv of the floor is flipped from 0 to 1.
+    let uv = &gltf["accessors"][attributes["TEXCOORD_0"].as_u64().unwrap() as usize];
+    let view = &gltf["bufferViews"][uv["bufferView"].as_u64().unwrap() as usize];
+    let offset = view["byteOffset"].as_u64().unwrap() as usize;
+    assert_eq!(f32::from_bits(u32_at(&bin, offset + 4)), 1.0);
+    for view in gltf["bufferViews"].as_array().unwrap() {
+        let offset = view["byteOffset"].as_u64().unwrap() as usize;
+        assert_eq!(offset % 4, 0);
+        assert!(offset + view["byteLength"].as_u64().unwrap() as usize <= bin.len());
+    }
+
+    let material = &gltf["materials"][primitives[0]["material"].as_u64().unwrap() as usize];
+    let pbr = &material["pbrMetallicRoughness"];
+    assert_eq!(pbr["baseColorFactor"], serde_json::json!([0.5, 0.5, 0.5, 0.5]));
+    assert_eq!(pbr["roughnessFactor"], 1.0);
+    assert_eq!(material["alphaMode"], "BLEND");
+    let image = pbr["baseColorTexture"]["index"].as_u64().unwrap() as usize;
+    assert_eq!(gltf["images"][gltf["textures"][image]["source"].as_u64().unwrap() as usize]["uri"],
+               "floor%20tiles.png");
+}
+
+#[test]
+fn exports_single_glb_buffer() {
+    let reader = read();
+    let glb = reader.scene.to_glb();
+    assert_eq!(&glb[..4], b"glTF");
+    assert_eq!(u32_at(&glb, 4), 2);
+    assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+    let json_length = u32_at(&glb, 12) as usize;
+    assert_eq!(json_length % 4, 0);
+    assert_eq!(&glb[16..20], b"JSON");
+    let gltf: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+    assert!(gltf["buffers"][0].get("uri").is_none());
+    let bin = 20 + json_length;
+    assert_eq!(&glb[bin + 4..bin + 8], b"BIN\0");
+    assert_eq!(u32_at(&glb, bin) as usize, glb.len() - bin - 8);
+    assert_eq!(gltf["buffers"][0]["byteLength"], glb.len() - bin - 8);
+}
@@ -0,0 +1,283 @@
+//! Test suite for parsing the .obj and .mtl data natively.
+
+extern crate obj;
+
+use cgmath::{InnerSpace, Matrix3, Rad, Vector3};
+use obj::data::{FaceType, TextureChannel};
+use obj::error::ObjError;
+use obj::options::LoadOptions;
+use obj::parser::Parser;
+use obj::resolver::MemoryResolver;
+use obj::triangulate::triangulate;
+
+#[test]
+fn reports_location_of_bad_float() {
+    let data = "o cube\nv 1.0 2.0 3.0\nv 1.0 abc 3.0\n";
+    let error = Parser::parse_named("cube.obj", data, "").err().unwrap();
+    match &error {
+        ObjError::BadFloat { location, token } => {
+            assert_eq!(location.file, "cube.obj");
+            assert_eq!(location.line, 3);
+            assert_eq!(location.column, 7);
+            assert_eq!(token, "abc");
+        }
+        _ => panic!("unexpected error {:?}", error),
+    }
+    assert_eq!(error.to_string(), "cube.obj:3:7: expected a number, found `abc`");
+}
+
+#[test]
+fn ignores_the_weight_of_vertices() {
+    let parser = Parser::parse("v 1 2 3 0\nv 1 2 3 2\n", "").unwrap();
+    assert_eq!(parser.model.borrow().gv, vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn reports_out_of_range_and_malformed_faces() {
+    let data = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n";
+    match Parser::parse(data, "").err().unwrap() {
+        ObjError::OutOfRange { location, token, count } => {
+            assert_eq!((location.line, location.column), (4, 7));
+            assert_eq!((token.as_str(), count), ("4", 3));
+        }
+        error => panic!("unexpected error {:?}", error),
+    }
+
+    let data = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1/1/1 2 3\n";
+    match Parser::parse(data, "").err().unwrap() {
+        ObjError::MalformedStatement { token, .. } => assert_eq!(token, "1/1/1/1"),
+        error => panic!("unexpected error {:?}", error),
+    }
+}
+
+#[test]
+fn warns_about_unknown_material() {
+    let data = "mtllib scene.mtl\nusemtl missing\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+    let material = "newmtl wood\nKd 0.5 0.3 0.1\n";
+    let parser = Parser::parse(data, material).unwrap();
+    let model = parser.model.borrow();
+    match &model.warnings[..] {
+        [ObjError::UnknownMaterial { location, name }] => {
+            assert_eq!((location.line, location.column), (2, 8));
+            assert_eq!(name, "missing");
+        }
+        warnings => panic!("unexpected warnings {:?}", warnings),
+    }
+    assert_eq!(model.meshes[0].faces[0].material.borrow().name, "default");
+}
+
+#[test]
+fn applies_default_material_set_after_creating_the_parser() {
+    let mut parser = Parser::new("scene.obj");
+    parser.options.set_default_material("fallback", &[1.0, 0.0, 0.0]);
+    parser.read("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", &MemoryResolver::new()).unwrap();
+    let model = parser.model.borrow();
+    let material = model.meshes[0].material.borrow();
+    assert_eq!(material.name, "fallback");
+    assert_eq!(material.diffuse, [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn parses_vertex_colors() {
+    let data = "v 0 0 0\nv 1 0 0 1 0 0\nv 0 1 0 0 1 0 0.5\nf 1 2 3\n";
+    let parser = Parser::parse(data, "").unwrap();
+    let model = parser.model.borrow();
+    assert_eq!(model.gv.len(), 9);
+    assert_eq!(
+        model.colors,
+        vec![1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.5]
+    );
+    assert!(model.meshes[0].has_vertex_colors);
+}
+
+#[test]
+fn parses_texture_maps() {
+    let data = "mtllib scene.mtl\nusemtl wood\n";
+    let material = "newmtl wood\nmap_Kd textures/oak wood.png\nbump oak_bump.png\n\
+                    norm oak_normal.png\nmap_d  oak alpha.png  \n";
+    let parser = Parser::parse(data, material).unwrap();
+    let model = parser.model.borrow();
+    let wood = model.matlib.1["wood"].borrow();
+    assert_eq!(wood.texture_diffuse.as_ref().unwrap().path, "textures/oak wood.png");
+    assert_eq!(wood.texture_bump.as_ref().unwrap().path, "oak_bump.png");
+    assert_eq!(wood.texture_normal.as_ref().unwrap().path, "oak_normal.png");
+    assert_eq!(wood.texture_dissolve.as_ref().unwrap().path, "oak alpha.png");
+}
+
+#[test]
+fn parses_texture_options() {
+    let data = "mtllib scene.mtl\nusemtl wood\n";
+    let material = "newmtl wood\n\
+                    map_Kd -o 0.5 0 0 -s 2 2 1 -clamp on -mm 0.1 wood.png\n\
+                    map_bump -bm 0.3 -imfchan l -blendu off -texres 512 -s 4 2.png\n";
+    let parser = Parser::parse(data, material).unwrap();
+    let model = parser.model.borrow();
+    let wood = model.matlib.1["wood"].borrow();
+    let diffuse = wood.texture_diffuse.as_ref().unwrap();
+    assert_eq!(diffuse.path, "wood.png");
+    assert_eq!(diffuse.offset, [0.5, 0.0, 0.0]);
+    assert_eq!(diffuse.scale, [2.0, 2.0, 1.0]);
+    assert!(diffuse.clamp);
+    assert_eq!((diffuse.base, diffuse.gain), (0.1, 1.0));
+    let bump = wood.texture_bump.as_ref().unwrap();
+    assert_eq!(bump.path, "2.png");
+    assert_eq!(bump.bump_multiplier, 0.3);
+    assert_eq!(bump.channel, Some(TextureChannel::Luminance));
+    assert!(!bump.blend_u && bump.blend_v);
+    assert_eq!(bump.resolution, Some(512));
+    assert_eq!(bump.scale, [4.0, 1.0, 1.0]);
+}
+
+#[test]
+fn parses_dissolve_and_emissive() {
+    let data = "mtllib scene.mtl\nusemtl glass\n";
+    let material = "newmtl glass\nd -halo 0.25\nKe 1 0.5 0\nnewmtl panel\nTr 0.1\n";
+    let parser = Parser::parse(data, material).unwrap();
+    let model = parser.model.borrow();
+    let glass = model.matlib.1["glass"].borrow();
+    assert_eq!(glass.dissolve, 0.25);
+    assert!(glass.dissolve_halo);
+    assert_eq!(glass.emissive, [1.0, 0.5, 0.0]);
+    let panel = model.matlib.1["panel"].borrow();
+    assert!((panel.transparency() - 0.1).abs() < 1e-6);
+    assert!(!panel.dissolve_halo);
+}
+
+#[test]
+fn parses_pbr_extension() {
+    let data = "mtllib scene.mtl\nusemtl metal\n";
+    let material = "newmtl metal\nPr 0.2\nPm 1\nPcr 0.05\naniso 0.5\n\
+                    map_RMA -s 2 2 1 metal_rma.png\nnewmtl plastic\nKd 1 1 1\n";
+    let parser = Parser::parse(data, material).unwrap();
+    let model = parser.model.borrow();
+    let metal = model.matlib.1["metal"].borrow();
+    let pbr = metal.pbr.as_ref().unwrap();
+    assert_eq!((pbr.roughness, pbr.metallic), (0.2, 1.0));
+    assert_eq!((pbr.clearcoat_roughness, pbr.anisotropy), (0.05, 0.5));
+    assert_eq!(pbr.texture_rma.as_ref().unwrap().path, "metal_rma.png");
+    assert!(model.matlib.1["plastic"].borrow().pbr.is_none());
+}
+
+/// L-shaped floor plan, starting at a corner that can't see the whole polygon.
+const L_SHAPE: &str = "v 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nv 0 0 0\nv 2 0 0\n\
+                       vt 2 1\nvt 1 1\nvt 1 2\nvt 0 2\nvt 0 0\nvt 2 0\n";
+
+#[test]
+fn triangulates_concave_polygons_by_ear_clipping() {
+    let data = format!("{}f 1/1 2/2 3/3 4/4 5/5 6/6\n", L_SHAPE);
+    let parser = Parser::parse(&data, "").unwrap();
+    let model = parser.model.borrow();
+    let face = &model.meshes[0].faces[0];
+    assert_eq!(face.vertices.len() % 3, 0);
+    assert_eq!(face.vertices, face.textures);
+    let mut total = 0.0;
+    for triangle in face.vertices.chunks(3) {
+        let p: Vec<(f32, f32)> = triangle.iter()
+            .map(|v| (model.gv[v * 3], model.gv[v * 3 + 1]))
+            .collect();
+        let area = ((p[1].0 - p[0].0) * (p[2].1 - p[0].1)
+            - (p[1].1 - p[0].1) * (p[2].0 - p[0].0)) / 2.0;
+        let center = ((p[0].0 + p[1].0 + p[2].0) / 3.0, (p[0].1 + p[1].1 + p[2].1) / 3.0);
+        assert!(area > 0.0);
+        assert!(center.0 <= 1.0 || center.1 <= 1.0);
+        total += area;
+    }
+    assert!((total - 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn triangulates_small_tilted_polygons() {
+    let outline = [(2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0), (0.0, 0.0), (2.0, 0.0)];
+    let rotation = Matrix3::from_axis_angle(Vector3::new(1.0, 2.0, 3.0f32).normalize(), Rad(0.7));
+    let polygon: Vec<Vector3<f32>> = outline.iter()
+        .map(|(x, y)| rotation * Vector3::new(x * 1e-4, y * 1e-4, 0.0) + Vector3::new(0.5, 0.25, 0.1))
+        .collect();
+    let mut total = 0.0;
+    for triangle in triangulate(&polygon) {
+        let p: Vec<(f32, f32)> = triangle.iter().map(|x| outline[*x]).collect();
+        let area = ((p[1].0 - p[0].0) * (p[2].1 - p[0].1)
+            - (p[1].1 - p[0].1) * (p[2].0 - p[0].0)) / 2.0;
+        let center = ((p[0].0 + p[1].0 + p[2].0) / 3.0, (p[0].1 + p[1].1 + p[2].1) / 3.0);
+        assert!(area > 0.0);
+        assert!(center.0 <= 1.0 || center.1 <= 1.0);
+        total += area;
+    }
+    assert!((total - 3.0f32).abs() < 1e-6);
+}
+
+#[test]
+fn skips_duplicate_and_collinear_corners() {
+    let data = "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 2 0 0\nv 2 1 0\nv 0 1 0\nf 1 2 3 4 5 6\n";
+    let parser = Parser::parse(data, "").unwrap();
+    let model = parser.model.borrow();
+    let face = &model.meshes[0].faces[0];
+    assert_eq!(face.vertices.len(), 6);
+    assert!(!face.vertices.contains(&1));
+}
+
+#[test]
+fn keeps_polygons_when_not_triangulating() {
+    let mut parser = Parser::new("plan.obj");
+    parser.options.triangulate = false;
+    parser.read(&format!("{}f 1 2 3 4 5 6\n", L_SHAPE), &MemoryResolver::new()).unwrap();
+    let model = parser.model.borrow();
+    let face = &model.meshes[0].faces[0];
+    assert_eq!(face.face_type, FaceType::Polygon);
+    assert_eq!(face.vertices, vec![0, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn skips_malformed_statements_when_lenient() {
+    let data = "v 0 0 0\nv 1 0 0\nv 0 x 0\nv 0 1 0\np 1\nl 1 2\nf 1 2 5\nf 1 2 3\n";
+    let mut options = LoadOptions::new();
+    options.strict = false;
+    options.ignore_points = true;
+    options.ignore_lines = true;
+    let mut parser = Parser::with_options("plan.obj", options);
+    parser.read(data, &MemoryResolver::new()).unwrap();
+    let model = parser.model.borrow();
+    assert_eq!(model.gv.len(), 9);
+    assert_eq!(model.meshes[0].faces.len(), 1);
+    assert_eq!(model.meshes[0].faces[0].face_type, FaceType::Triangle);
+    assert_eq!(model.warnings.len(), 2);
+    assert_eq!(model.warnings[0].location().line, 3);
+    assert_eq!(model.warnings[1].location().line, 7);
+}
+
+#[test]
+fn skips_malformed_material_statements_when_lenient() {
+    let data = "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl blue\nf 1 2 3\n";
+    let material = "newmtl red\nKd x 0 0\nnewmtl blue\nKd 0 0 1\n";
+    let mut options = LoadOptions::new();
+    options.strict = false;
+    let mut parser = Parser::with_options("scene.obj", options);
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("scene.mtl", material);
+    parser.read(data, &resolver).unwrap();
+    let model = parser.model.borrow();
+    assert_eq!(model.meshes[0].material.borrow().name, "blue");
+    assert_eq!(model.meshes[0].material.borrow().diffuse, [0.0, 0.0, 1.0]);
+    match &model.warnings[..] {
+        [ObjError::BadFloat { location, token }] => {
+            assert_eq!((location.file.as_str(), location.line), ("scene.mtl", 2));
+            assert_eq!(token, "x");
+        },
+        warnings => panic!("unexpected warnings {:?}", warnings),
+    }
+    assert!(Parser::parse(data, material).is_err());
+}
+
+#[test]
+fn records_group_membership_of_faces() {
+    let data = "o car\nv 0 0 0\nv 1 0 0\nv 0 1 0\ng body doors\nf 1 2 3\ng wheels\nmg 2 0.5\n\
+                f 1 2 3\ng\nmg off\nf 1 2 3\n";
+    let parser = Parser::parse(data, "").unwrap();
+    let model = parser.model.borrow();
+    assert_eq!(model.objects.len(), 1);
+    assert_eq!(model.grplib.1, vec!["body", "doors", "wheels"]);
+    let faces = &model.meshes[0].faces;
+    assert_eq!(faces[0].groups, vec![0, 1]);
+    assert_eq!((faces[1].groups.clone(), faces[1].merging_group), (vec![2], 2));
+    assert_eq!((faces[2].groups.clone(), faces[2].merging_group), (vec![], 0));
+    assert_eq!(model.merging_resolutions.get(&2), Some(&0.5));
+}
@@ -0,0 +1,313 @@
+//! Test suite for building the scene graph natively.
+
+extern crate cgmath;
+extern crate obj;
+
+use cgmath::{Matrix4, Vector3};
+use obj::data::Material;
+use obj::error::ObjError;
+use obj::reader::ObjReader;
+use obj::resolver::MemoryResolver;
+
+const CUBE_FACE: &str = "mtllib cube.mtl
+o cube
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+";
+
+const CUBE_MATERIAL: &str = "newmtl red
+Kd 1 0 0
+";
+
+#[test]
+fn reads_scene_without_js() {
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(CUBE_FACE, CUBE_MATERIAL).unwrap();
+    let scene = &reader.scene;
+    assert_eq!(scene.nodes.len(), 2);
+    assert_eq!(scene.nodes[1].name, "cube");
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.meshes[0].face_indices.len(), 6);
+    assert_eq!(scene.meshes[0].gv.len(), 18);
+    assert_eq!(scene.materials.len(), 1);
+    assert_eq!(scene.materials[0].diffuse, [1.0, 0.0, 0.0]);
+    assert_eq!(scene.meshes[0].material.as_ref().unwrap().index, 0);
+}
+
+#[test]
+fn merges_shared_corners_when_indexed() {
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.indexed = true;
+    reader.read(CUBE_FACE, CUBE_MATERIAL).unwrap();
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.gv.len(), 12);
+    assert_eq!(mesh.vt.len(), 8);
+    assert_eq!(mesh.vn.len(), 12);
+    assert_eq!(mesh.face_indices, vec![0, 1, 2, 0, 2, 3]);
+}
+
+#[test]
+fn keeps_points_and_lines_apart_from_triangles() {
+    let data = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\n\
+                f 1 2 3\nl 1/1 2/2 3/1\nl 3 4\np 1 4\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.indexed = true;
+    reader.read(data, "").unwrap();
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.triangles, 0..3);
+    assert_eq!(mesh.line_strips, vec![3..6, 6..8]);
+    assert_eq!(mesh.points, 8..10);
+    assert_eq!(mesh.faces.len(), 4);
+    assert_eq!(mesh.faces[1].face_type, obj::data::FaceType::Line);
+}
+
+#[test]
+fn loads_every_material_library_through_the_resolver() {
+    let mut resources = MemoryResolver::new();
+    resources.insert(
+        "models/car.obj",
+        "mtllib body.mtl ../shared/glass.mtl\no car\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+         usemtl paint\nf 1 2 3\n",
+    );
+    resources.insert("models/body.mtl", "newmtl paint\nKd 1 0 0\nmap_Kd textures/paint.png\n");
+    resources.insert("shared/glass.mtl", "newmtl glass\nd 0.2\n");
+    let mut reader = ObjReader::new().unwrap();
+    reader.read_with("models/car.obj", &resources).unwrap();
+    let mut names: Vec<&str> = reader.scene.materials.iter().map(|m| m.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["glass", "paint"]);
+    let paint = reader.scene.materials.iter().find(|m| m.name == "paint").unwrap();
+    let texture = paint.texture_diffuse.as_ref().unwrap();
+    assert_eq!(texture.path, "textures/paint.png");
+    assert_eq!(texture.resolved, "models/textures/paint.png");
+
+    match ObjReader::new().unwrap().read_with("models/missing.obj", &resources) {
+        Err(ObjError::MissingResource { path, location, .. }) => {
+            assert_eq!(path, "models/missing.obj");
+            assert_eq!(location.line, 0);
+            assert_eq!(location.to_string(), "models/missing.obj");
+        },
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn uses_default_material_for_faces_without_one() {
+    let data = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nmtllib cube.mtl\nusemtl red\nf 3 2 1\n\
+                usemtl blue\nf 1 3 2\n";
+    let mut default = Material::new();
+    default.name = String::from("fallback");
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.default_material = Some(default);
+    reader.read(data, CUBE_MATERIAL).unwrap();
+    let scene = &reader.scene;
+    assert_eq!(scene.meshes.len(), 2);
+    let names: Vec<&str> = scene.meshes.iter()
+        .map(|mesh| scene.materials[mesh.material.as_ref().unwrap().index].name.as_str())
+        .collect();
+    assert_eq!(names, vec!["fallback", "red"]);
+    assert_eq!(scene.meshes[0].faces.len(), 2);
+    assert_eq!(reader.warnings.len(), 1);
+
+    let mut reader = ObjReader::new().unwrap();
+    reader.read("mtllib cube.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", CUBE_MATERIAL).unwrap();
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(reader.scene.materials[mesh.material.as_ref().unwrap().index].name, "default");
+}
+
+/// Two triangles folded along the edge between the first and third vertex.
+const FOLDED: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\n";
+
+fn read_indexed(data: &str) -> ObjReader {
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.indexed = true;
+    reader.read(data, "").unwrap();
+    reader
+}
+
+#[test]
+fn generates_smoothed_and_flat_normals() {
+    let reader = read_indexed(&format!("{}s 1\nf 1 2 3\nf 1 3 4\n", FOLDED));
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.gv.len(), 12);
+    let half = 0.5f32.sqrt();
+    let shared = &mesh.vn[..3];
+    assert!((shared[0] - half).abs() < 1e-6 && shared[1].abs() < 1e-6);
+    assert!((shared[2] - half).abs() < 1e-6);
+
+    let reader = read_indexed(&format!("{}s off\nf 1 2 3\nf 1 3 4\n", FOLDED));
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.gv.len(), 18);
+    assert_eq!(&mesh.vn[..3], &[0.0, 0.0, 1.0]);
+    assert_eq!(&mesh.vn[9..12], &[1.0, 0.0, 0.0]);
+
+    let reader = read_indexed(&format!("{}s 1\nf 1 2 3\ns 2\nf 1 3 4\n", FOLDED));
+    assert_eq!(reader.scene.meshes[0].gv.len(), 18);
+}
+
+#[test]
+fn generates_tangents_and_splits_mirrored_vertices() {
+    let data = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv -1 0 0\nv -1 1 0\n\
+                vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 0 1\n\
+                f 1/1/1 2/2/1 3/3/1 4/4/1\nf 5/2/1 1/1/1 4/4/1 6/3/1\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.indexed = true;
+    reader.options.generate_tangents = true;
+    reader.read(data, "").unwrap();
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.gv.len(), 8 * 3);
+    assert_eq!(mesh.tangents.len(), 8 * 4);
+    assert_eq!(&mesh.tangents[..4], &[1.0, 0.0, 0.0, 1.0]);
+    let mirrored = mesh.faces[1].indices[0];
+    assert_eq!(&mesh.tangents[mirrored * 4..mirrored * 4 + 4], &[-1.0, 0.0, 0.0, -1.0]);
+    assert_ne!(mesh.faces[1].indices[1], mesh.faces[0].indices[0]);
+}
+
+/// Side of the cube of the MikkTSpace reference tests, with its tangents computed by MikkTSpace.
+const MIKKTSPACE_SIDE: &str = "v 0.5 -0.5 0.5\nv 0.5 -0.5 -0.5\nv 0.5 0.5 -0.5\nv 0.5 0.5 0.5\nv 0.5 0 0\n\
+                               vt 0 0\nvt 0 1\nvt 1 1\nvt 1 0\nvt 0.5 0.5\n\
+                               vn 1 -1 1\nvn 1 -1 -1\nvn 1 1 -1\nvn 1 1 1\nvn 1 0 0\n\
+                               f 1/1/1 2/2/2 5/5/5\nf 2/2/2 3/3/3 5/5/5\nf 3/3/3 4/4/4 5/5/5\nf 4/4/4 1/1/1 5/5/5\n";
+
+#[test]
+fn generates_the_tangents_of_mikktspace() {
+    let expected = [
+        [0.40824825, 0.81649655, 0.40824825, -1.0],
+        [0.40824825, 0.81649655, -0.40824825, -1.0],
+        [-0.40824825, 0.81649655, 0.40824825, -1.0],
+        [-0.40824825, 0.81649655, -0.40824825, -1.0],
+        [0.0, 1.0, 0.0, -1.0],
+    ];
+    for indexed in [true, false].iter() {
+        let mut reader = ObjReader::new().unwrap();
+        reader.options.indexed = *indexed;
+        reader.options.generate_tangents = true;
+        reader.read(MIKKTSPACE_SIDE, "").unwrap();
+        let mesh = &reader.scene.meshes[0];
+        assert_eq!(mesh.tangents.len(), mesh.gv.len() / 3 * 4);
+        for (vertex, position) in mesh.gv.chunks(3).enumerate() {
+            let corner = MIKKTSPACE_SIDE.lines()
+                .position(|x| x == format!("v {} {} {}", position[0], position[1], position[2]))
+                .unwrap();
+            let tangent = &mesh.tangents[vertex * 4..vertex * 4 + 4];
+            for (value, reference) in tangent.iter().zip(expected[corner].iter()) {
+                assert!((value - reference).abs() < 1e-5, "{:?} != {:?}", tangent, expected[corner]);
+            }
+        }
+    }
+}
+
+#[test]
+fn keeps_face_indices_of_faces_across_a_mirror_seam() {
+    let data = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 2 0\nvn 0 0 1\n\
+                f 1/1/1 2/2/1 3/3/1 4/4/1\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.indexed = true;
+    reader.options.generate_tangents = true;
+    reader.read(data, "").unwrap();
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.gv.len(), 6 * 3);
+    let face = &mesh.faces[0];
+    assert_eq!(face.indices, mesh.face_indices[face.triangles.clone()].to_vec());
+}
+
+#[test]
+fn triangulates_kept_polygons_for_drawing() {
+    let data = "v 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nv 0 0 0\nv 2 0 0\nf 1 2 3 4 5 6\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.options.triangulate = false;
+    reader.read(data, "").unwrap();
+    let mesh = &reader.scene.meshes[0];
+    assert_eq!(mesh.faces[0].indices, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(mesh.triangles.len(), 12);
+    // The triangles don't overlap when they all wind the same way and add up to the area of the L.
+    let mut total = 0.0;
+    for triangle in mesh.face_indices[mesh.triangles.clone()].chunks(3) {
+        let p: Vec<(f32, f32)> = triangle.iter().map(|v| (mesh.gv[v * 3], mesh.gv[v * 3 + 1])).collect();
+        let area = ((p[1].0 - p[0].0) * (p[2].1 - p[0].1) - (p[1].1 - p[0].1) * (p[2].0 - p[0].0)) / 2.0;
+        assert!(area > 0.0);
+        total += area;
+    }
+    assert!((total - 3.0f32).abs() < 1e-6);
+    assert_eq!(&mesh.vn[..3], &[0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn exposes_groups_as_face_selections() {
+    let data = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\ng body\nf 1 2 3 4\ng body wheels\nf 1 2 3\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, "").unwrap();
+    let scene = &reader.scene;
+    assert_eq!(scene.groups, vec!["body", "wheels"]);
+    assert_eq!(scene.group_meshes("wheels"), vec![0]);
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.groups["body"], vec![0, 1]);
+    assert_eq!(mesh.group_triangles("body").len(), 9);
+    assert_eq!(mesh.group_triangles("wheels"), vec![6, 7, 8]);
+    assert!(mesh.group_triangles("doors").is_empty());
+}
+
+#[test]
+fn builds_hierarchy_from_object_and_group_names() {
+    let data = "v 0 0 0\nv 1 0 0\nv 0 1 0\no car\nf 1 2 3\no car/wheel\ng rim tyre/tread\nf 1 2 3\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, "").unwrap();
+    let scene = &mut reader.scene;
+    let names: Vec<&str> = scene.nodes.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["", "car", "wheel", "rim", "tyre", "tread"]);
+    assert_eq!(scene.nodes[0].children, vec![1]);
+    assert_eq!(scene.nodes[1].children, vec![2]);
+    assert_eq!(scene.nodes[2].children, vec![3, 4]);
+    assert_eq!(scene.nodes[4].children, vec![5]);
+    for (index, node) in scene.nodes.iter().enumerate().skip(1) {
+        let parent = node.parent.as_ref().unwrap().index;
+        assert!(scene.nodes[parent].children.contains(&index));
+    }
+    assert_eq!(scene.nodes[2].meshes, vec![1]);
+    assert_eq!(scene.nodes[5].group.as_deref(), Some("tyre/tread"));
+    assert_eq!(scene.nodes[4].group, None);
+
+    scene.nodes[1].transformation = Matrix4::from_translation(Vector3::new(1.0, 0.0, 0.0));
+    scene.nodes[2].transformation = Matrix4::from_translation(Vector3::new(0.0, 2.0, 0.0));
+    let world = scene.world_transform(5);
+    assert_eq!(world.w.truncate(), Vector3::new(1.0, 2.0, 0.0));
+    assert_eq!(scene.world_transforms()[2], world);
+}
+
+#[test]
+fn traverses_and_queries_the_scene() {
+    let data = "mtllib cube.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\no car\nusemtl red\nf 1 2 3\n\
+                o car/wheel\nf 1 2 3\no lamp\nf 1 2 3\n";
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, CUBE_MATERIAL).unwrap();
+    let scene = &mut reader.scene;
+    scene.nodes[1].transformation = Matrix4::from_translation(Vector3::new(1.0, 0.0, 0.0));
+
+    let depth_first: Vec<(&str, usize)> = scene.depth_first()
+        .map(|x| (x.node.name.as_str(), x.depth))
+        .collect();
+    assert_eq!(depth_first, vec![("", 0), ("car", 1), ("wheel", 2), ("lamp", 1)]);
+    let breadth_first: Vec<&str> = scene.breadth_first().map(|x| x.node.name.as_str()).collect();
+    assert_eq!(breadth_first, vec!["", "car", "lamp", "wheel"]);
+    let wheel = scene.depth_first().find(|x| x.node.name == "wheel").unwrap();
+    assert_eq!(wheel.world.w.truncate(), Vector3::new(1.0, 0.0, 0.0));
+
+    let car = scene.find_node("car").unwrap();
+    let wheel = scene.find_node("wheel").unwrap();
+    assert_eq!(scene.ancestors(wheel), vec![car, 0]);
+    assert_eq!(scene.descendants(0), vec![car, wheel, scene.find_node("lamp").unwrap()]);
+    assert_eq!(scene.node_meshes(car).len(), 1);
+    assert_eq!(scene.node_materials(car)[0].name, "red");
+    assert_eq!(scene.find_material("red"), Some(0));
+    assert_eq!(scene.find_mesh("lamp"), Some(2));
+    assert_eq!(scene.find_node("door"), None);
+}
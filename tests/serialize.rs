@@ -0,0 +1,88 @@
+//! Test suite for serializing the parsed model and the scene.
+
+extern crate obj;
+extern crate serde_json;
+
+use std::rc::Rc;
+
+use obj::data::Model;
+use obj::parser::Parser;
+use obj::reader::{ObjReader, SceneGraph};
+
+const DATA: &str = "mtllib cube.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\n\
+                    o cube/side\ng front\nusemtl red\nf 1/1/1 2/1/1 3/1/1 4/1/1\nl 1 2\np 3\n";
+
+const MATERIAL: &str = "newmtl red\nKd 1 0 0\nmap_Kd -s 2 2 red.png\n";
+
+#[test]
+fn round_trips_scene_through_json() {
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(DATA, MATERIAL).unwrap();
+    let json = serde_json::to_string(&reader.scene).unwrap();
+    let scene: SceneGraph = serde_json::from_str(&json).unwrap();
+    assert_eq!(scene, reader.scene);
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let transformation = &value["nodes"][1]["transformation"];
+    assert_eq!(transformation.as_array().unwrap().len(), 16);
+    assert_eq!(transformation[0], 1.0);
+    assert_eq!(transformation[1], 0.0);
+}
+
+#[test]
+fn serializes_model_with_its_materials() {
+    let parser = Parser::parse(DATA, MATERIAL).unwrap();
+    let json = serde_json::to_string(&*parser.model.borrow()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["meshes"][0]["material"], "red");
+    assert_eq!(value["meshes"][0]["faces"][0]["material"], "red");
+    assert_eq!(value["matlib"][1]["red"]["diffuse"], serde_json::json!([1.0, 0.0, 0.0]));
+    assert_eq!(value["grplib"][1][0], "front");
+
+    let model: Model = serde_json::from_str(&json).unwrap();
+    let red = &model.matlib.1["red"];
+    assert_eq!(model.meshes[0].faces.len(), 3);
+    assert!(Rc::ptr_eq(&model.meshes[0].material, red));
+    assert!(model.meshes[0].faces.iter().all(|x| Rc::ptr_eq(&x.material, red)));
+    assert!(Rc::ptr_eq(&model.matlib.0, red));
+    assert_eq!(serde_json::to_string(&model).unwrap(), json);
+
+    let mut reader = ObjReader::new().unwrap();
+    reader.read_model(&model).unwrap();
+    let mut expected = ObjReader::new().unwrap();
+    expected.read(DATA, MATERIAL).unwrap();
+    assert_eq!(reader.scene, expected.scene);
+}
+
+#[test]
+fn links_faces_without_material_to_the_default_one() {
+    let parser = Parser::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "").unwrap();
+    let json = serde_json::to_string(&*parser.model.borrow()).unwrap();
+    let model: Model = serde_json::from_str(&json).unwrap();
+    assert!(Rc::ptr_eq(&model.meshes[0].material, &model.default_material));
+    let mut reader = ObjReader::new().unwrap();
+    reader.read_model(&model).unwrap();
+    assert_eq!(reader.scene.materials.len(), 1);
+    assert!(reader.scene.meshes[0].material.is_some());
+}
+
+#[test]
+fn serializes_the_same_json_on_every_read() {
+    let data = "mtllib many.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl d\ng d\nf 1 2 3\nusemtl b\ng b\nf 1 2 3\n\
+                usemtl a\ng a\nf 1 2 3\nusemtl c\ng c\nf 1 2 3\n";
+    let material = "newmtl d\nnewmtl b\nnewmtl a\nnewmtl c\n";
+    let read = || {
+        let mut reader = ObjReader::new().unwrap();
+        reader.read(data, material).unwrap();
+        serde_json::to_string(&reader.scene).unwrap()
+    };
+    let json = read();
+    for _ in 0..4 {
+        assert_eq!(read(), json);
+    }
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let names: Vec<&str> = value["materials"].as_array().unwrap().iter()
+        .map(|x| x["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["a", "b", "c", "d"]);
+}
@@ -0,0 +1,58 @@
+//! Test suite for parsing the .obj data as it arrives in chunks.
+
+extern crate futures;
+extern crate obj;
+
+use futures::executor::block_on;
+use futures::stream;
+use obj::parser::Parser;
+use obj::resolver::MemoryResolver;
+use obj::streaming::{parse_stream, StreamingParser};
+
+const SCENE: &str = "# split me\r\nmtllib scene.mtl\r\no quad\r\nv 0 0 0\r\nv 1 0 0\r\n\
+                     v 1 1 0\r\nv 0 1 0\r\nusemtl red\r\nf 1 2 3 4\r\nl 1 3";
+
+fn resources() -> MemoryResolver {
+    let mut resources = MemoryResolver::new();
+    resources.insert("scene.mtl", "newmtl red\nKd 1 0 0\n");
+    resources
+}
+
+#[test]
+fn parses_lines_split_across_chunks() {
+    let resources = resources();
+    let expected = Parser::parse_with("scene.obj", SCENE, &resources).unwrap();
+    let expected = expected.model.borrow();
+    for size in 1..8 {
+        let mut parser = StreamingParser::new("scene.obj", &resources);
+        for chunk in SCENE.as_bytes().chunks(size) {
+            parser.feed(chunk).unwrap();
+        }
+        let parser = parser.finish().unwrap();
+        let model = parser.model.borrow();
+        assert_eq!(model.gv, expected.gv);
+        assert_eq!(model.objects[0].name, "quad");
+        assert_eq!(model.meshes[0].faces.len(), expected.meshes[0].faces.len());
+        assert!(model.matlib.1.contains_key("red"));
+    }
+}
+
+#[test]
+fn parses_stream_of_chunks() {
+    let resources = resources();
+    let chunks: Vec<Vec<u8>> = SCENE.as_bytes().chunks(5).map(|x| x.to_vec()).collect();
+    let parser = block_on(parse_stream("scene.obj", stream::iter(chunks), &resources)).unwrap();
+    let model = parser.model.borrow();
+    assert_eq!(model.gv.len(), 12);
+    assert_eq!(model.meshes[0].faces.len(), 2);
+}
+
+#[test]
+fn reports_line_of_error_across_chunks() {
+    let resources = resources();
+    let mut parser = StreamingParser::new("scene.obj", &resources);
+    parser.feed(b"v 0 0 0\nv 1 0").unwrap();
+    let error = parser.feed(b" x\n").err().unwrap();
+    assert_eq!(error.location().line, 2);
+    assert_eq!(error.location().column, 7);
+}
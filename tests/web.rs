@@ -2,6 +2,7 @@
 
 #![cfg(target_arch = "wasm32")]
 
+extern crate obj;
 extern crate wasm_bindgen_test;
 use wasm_bindgen_test::*;
 
@@ -11,3 +12,14 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+#[wasm_bindgen_test]
+fn exposes_scene_as_typed_arrays() {
+    let scene = obj::load_scene("v 0 0 0\nv 1 0 0\nv 0 1 0\no tri\nf 1 2 3\n").unwrap();
+    assert_eq!(scene.mesh_count(), 1);
+    let mesh = scene.mesh(0).unwrap();
+    assert_eq!(mesh.positions().to_vec(), vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    assert_eq!(mesh.triangles().to_vec(), vec![0, 1, 2]);
+    let node = scene.node(scene.find_node("tri").unwrap()).unwrap();
+    assert_eq!(node.meshes().to_vec(), vec![0]);
+}
@@ -0,0 +1,147 @@
+//! Test suite for writing the model and the scene back as .obj and .mtl text.
+
+extern crate obj;
+
+use obj::data::{Model, TextureChannel};
+use obj::options::LoadOptions;
+use obj::parser::Parser;
+use obj::reader::ObjReader;
+use obj::resolver::MemoryResolver;
+use obj::writer::ObjWriter;
+
+const DATA: &str = "mtllib scene.mtl\nv 0 0 0\nv 2 0 0\nv 2 1 0\nv 0 1 0\nv 1 2 0\nvt 0 0\nvt 1 0\nvt 1 1\n\
+                    vn 0 0 1\no floor\nusemtl tiles\ng base\ns 1\nf 1/1/1 2/2/1 3/3/1 4/3/1\ns off\n\
+                    g base top\nf 3//1 5//1 4//1\no lines\nl 1 2 3\np 5\n";
+
+const MATERIAL: &str = "newmtl tiles\nKd 0.5 0.25 0.125\nNs 10\nd -halo 0.5\nillum 2\nPr 0.3\n\
+                        map_Kd -s 2 2 1 -clamp on floor.png\nbump -bm 0.5 -imfchan l bump.png\n";
+
+fn parse(data: &str, material: &str) -> Model {
+    let options = LoadOptions { triangulate: false, ..LoadOptions::new() };
+    let mut parser = Parser::with_options("scene.obj", options);
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("scene.mtl", material);
+    parser.read(data, &resolver).unwrap();
+    let model = parser.model.borrow().clone();
+    model
+}
+
+#[test]
+fn round_trips_the_model() {
+    let model = parse(DATA, MATERIAL);
+    let (data, material) = ObjWriter::new().write_model(&model, "scene.mtl");
+    assert!(data.starts_with("mtllib scene.mtl\n"));
+    assert!(data.contains("g base\ns 1\nf 1/1/1 2/2/1 3/3/1 4/3/1\ng base top\ns off\nf 3//1 5//1 4//1\n"));
+    assert!(data.contains("o lines\nusemtl tiles\nl 1 2 3\np 5\n"));
+
+    let copy = parse(&data, &material);
+    assert_eq!(copy.gv, model.gv);
+    assert_eq!(copy.vt, model.vt);
+    assert_eq!(copy.grplib.1, model.grplib.1);
+    let faces = |model: &Model| model.meshes.iter()
+        .flat_map(|x| x.faces.iter().map(|f| (f.face_type, f.vertices.clone(), f.groups.clone(), f.smoothing_group)))
+        .collect::<Vec<_>>();
+    assert_eq!(faces(&copy), faces(&model));
+
+    let tiles = copy.matlib.1["tiles"].borrow().clone();
+    assert_eq!(tiles, model.matlib.1["tiles"].borrow().clone());
+    assert!(tiles.dissolve_halo);
+    let bump = tiles.texture_bump.unwrap();
+    assert_eq!(bump.bump_multiplier, 0.5);
+    assert_eq!(bump.channel, Some(TextureChannel::Luminance));
+    assert!(tiles.texture_diffuse.unwrap().clamp);
+}
+
+#[test]
+fn writes_relative_indices_precision_and_triangles() {
+    let model = parse("v 0.123456 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n", "");
+    let writer = ObjWriter { precision: 2, relative_indices: true, keep_polygons: false };
+    let (data, material) = writer.write_model(&model, "");
+    assert!(data.starts_with("v 0.12 0 0\nv 1 0 0\n"));
+    assert!(data.contains("f -4 -3 -2\nf -4 -2 -1\n"));
+    assert!(!data.contains("mtllib"));
+    assert!(material.starts_with("newmtl default\n"));
+}
+
+#[test]
+fn writes_the_scene_in_world_space() {
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(DATA, MATERIAL).unwrap();
+    let (data, _) = ObjWriter::new().write_scene(&reader.scene, "scene.mtl");
+
+    let mut copy = ObjReader::new().unwrap();
+    copy.read(&data, MATERIAL).unwrap();
+    assert_eq!(copy.scene.meshes.len(), reader.scene.meshes.len());
+    assert_eq!(copy.scene.groups, reader.scene.groups);
+    assert_eq!(copy.scene.bounds().aabb, reader.scene.bounds().aabb);
+    assert_eq!(copy.scene.materials, reader.scene.materials);
+}
+
+const OBJECTS: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nv 3 0 0\nv 2 1 0\nvn 0 0 1\n\
+                       o a\ns 1\nf 1//1 2//1 3//1\no b\ns off\nf 4//1 5//1 6//1\n";
+
+fn read_scene(data: &str) -> ObjReader {
+    let mut reader = ObjReader::new().unwrap();
+    reader.read(data, "").unwrap();
+    reader
+}
+
+fn positions(reader: &ObjReader) -> Vec<Vec<f32>> {
+    reader.scene.meshes.iter().map(|x| x.gv.clone()).collect()
+}
+
+#[test]
+fn writes_each_mesh_of_the_scene_after_the_previous_ones() {
+    let reader = read_scene(OBJECTS);
+    let (data, _) = ObjWriter::new().write_scene(&reader.scene, "");
+    assert!(data.contains("usemtl default\ns 1\nf 1//1 2//2 3//3\n"));
+    assert!(data.contains("usemtl default\ns off\nf 4//4 5//5 6//6\n"));
+
+    let copy = read_scene(&data);
+    assert_eq!(positions(&copy), positions(&reader));
+    let smoothing: Vec<u32> = copy.scene.meshes.iter().map(|x| x.faces[0].smoothing_group).collect();
+    assert_eq!(smoothing, vec![1, 0]);
+}
+
+#[test]
+fn writes_the_scene_with_relative_indices() {
+    let reader = read_scene(OBJECTS);
+    let writer = ObjWriter { relative_indices: true, ..ObjWriter::new() };
+    let (data, _) = writer.write_scene(&reader.scene, "");
+    assert!(data.contains("usemtl default\ns 1\nf -3//-3 -2//-2 -1//-1\n"));
+    assert!(data.contains("usemtl default\ns off\nf -3//-3 -2//-2 -1//-1\n"));
+    assert_eq!(positions(&read_scene(&data)), positions(&reader));
+}
+
+#[test]
+fn keeps_names_with_spaces() {
+    let data = "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\no my object\nusemtl my mat\nf 1 2 3\n";
+    let material = "newmtl my mat\nKd 1 0 0\n";
+    let model = parse(data, material);
+    assert_eq!(model.objects[0].name, "my object");
+    assert_eq!(model.meshes[0].material.borrow().name, "my mat");
+    assert!(model.warnings.is_empty());
+
+    let (data, material) = ObjWriter::new().write_model(&model, "scene.mtl");
+    assert!(data.contains("o my object\nusemtl my mat\n"));
+    assert!(material.starts_with("newmtl my mat\n"));
+    let copy = parse(&data, &material);
+    assert_eq!(copy.objects[0].name, "my object");
+    assert_eq!(copy.meshes[0].material.borrow().name, "my mat");
+}
+
+#[test]
+fn round_trips_the_points_and_lines_of_the_scene() {
+    let data = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\no a\nf 1/1/1 2/1/1 3/1/1\nl 1 2\np 3\n";
+    let reader = read_scene(data);
+    let (data, _) = ObjWriter::new().write_scene(&reader.scene, "");
+    assert_eq!(data.matches("vn ").count(), 3);
+    assert!(data.contains("\nl 4/4 5/5\np 6\n"));
+
+    let copy = read_scene(&data);
+    assert_eq!(positions(&copy), positions(&reader));
+    let types = |reader: &ObjReader| reader.scene.meshes[0].faces.iter()
+        .map(|x| x.face_type)
+        .collect::<Vec<_>>();
+    assert_eq!(types(&copy), types(&reader));
+}